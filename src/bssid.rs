@@ -0,0 +1,104 @@
+#![deny(missing_docs)]
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::ParseError;
+
+/// Hardware address of an access point, eg `00:11:22:33:44:55`
+///
+/// # Examples
+///
+/// ```
+/// use wpactrl::Bssid;
+/// let bssid: Bssid = "00:11:22:33:44:AA".parse().unwrap();
+/// assert_eq!(bssid, Bssid::from([0x00, 0x11, 0x22, 0x33, 0x44, 0xaa]));
+/// assert_eq!(bssid.to_string(), "00:11:22:33:44:aa");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bssid([u8; 6]);
+
+impl Bssid {
+    /// The six octets of the address
+    #[must_use]
+    pub fn octets(self) -> [u8; 6] {
+        self.0
+    }
+}
+
+impl From<[u8; 6]> for Bssid {
+    fn from(octets: [u8; 6]) -> Self {
+        Self(octets)
+    }
+}
+
+impl From<Bssid> for [u8; 6] {
+    fn from(bssid: Bssid) -> Self {
+        bssid.0
+    }
+}
+
+impl FromStr for Bssid {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseError::new(format!("invalid BSSID {:?}", s));
+        let mut octets = [0; 6];
+        let mut parts = s.split(':');
+        for octet in &mut octets {
+            let part = parts.next().ok_or_else(invalid)?;
+            if part.len() != 2 || !part.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(invalid());
+            }
+            *octet = u8::from_str_radix(part, 16).map_err(|_| invalid())?;
+        }
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(Self(octets))
+    }
+}
+
+impl TryFrom<&str> for Bssid {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Bssid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(
+            f,
+            "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+            a, b, c, d, e, g
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let bssid = Bssid::try_from("66:77:88:99:AA:bb").unwrap();
+        assert_eq!(bssid.octets(), [0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb]);
+        assert_eq!(bssid.to_string(), "66:77:88:99:aa:bb");
+        for invalid in [
+            "",
+            "66:77:88:99:aa",
+            "66:77:88:99:aa:bb:cc",
+            "66:77:88:99:aa:b",
+            "66:77:88:99:aa:+b",
+            "667788:99:aa:bb",
+        ] {
+            assert!(
+                invalid.parse::<Bssid>().is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+}
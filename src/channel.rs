@@ -0,0 +1,90 @@
+#![deny(missing_docs)]
+
+/// A Wi-Fi frequency band, needed to turn a channel number back into a frequency
+///
+/// Channel numbers are only unique within a band: channel 1 is 2412 MHz on
+/// 2.4 GHz but 5955 MHz on 6 GHz.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Band {
+    /// 2.4 GHz, channels 1 to 14
+    TwoPointFourGhz,
+    /// 5 GHz, channels 32 to 177
+    FiveGhz,
+    /// 6 GHz, odd channels 1 to 233 and channel 2
+    SixGhz,
+}
+
+/// The channel number of a frequency in MHz, or `None` if it is not a 2.4, 5 or 6 GHz channel
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(wpactrl::freq_to_channel(2412), Some(1));
+/// assert_eq!(wpactrl::freq_to_channel(2484), Some(14));
+/// assert_eq!(wpactrl::freq_to_channel(5180), Some(36));
+/// assert_eq!(wpactrl::freq_to_channel(5955), Some(1));
+/// assert_eq!(wpactrl::freq_to_channel(2413), None);
+/// ```
+#[must_use]
+pub fn freq_to_channel(freq: u32) -> Option<u32> {
+    match freq {
+        // Channel 14 sits apart from the others, outside the 5 MHz grid
+        2484 => Some(14),
+        2412..=2472 if (freq - 2407) % 5 == 0 => Some((freq - 2407) / 5),
+        5160..=5885 if freq % 5 == 0 => Some((freq - 5000) / 5),
+        // Channel 2 is the only even 6 GHz channel, and lies below channel 1
+        5935 => Some(2),
+        5955..=7115 if (freq - 5955) % 10 == 0 => Some((freq - 5950) / 5),
+        _ => None,
+    }
+}
+
+/// The frequency in MHz of a channel in `band`, or `None` if there is no such channel
+///
+/// # Examples
+///
+/// ```
+/// use wpactrl::{channel_to_freq, Band};
+/// assert_eq!(channel_to_freq(6, Band::TwoPointFourGhz), Some(2437));
+/// assert_eq!(channel_to_freq(14, Band::TwoPointFourGhz), Some(2484));
+/// assert_eq!(channel_to_freq(149, Band::FiveGhz), Some(5745));
+/// assert_eq!(channel_to_freq(37, Band::SixGhz), Some(6135));
+/// assert_eq!(channel_to_freq(15, Band::TwoPointFourGhz), None);
+/// ```
+#[must_use]
+pub fn channel_to_freq(channel: u32, band: Band) -> Option<u32> {
+    match (band, channel) {
+        (Band::TwoPointFourGhz, 14) => Some(2484),
+        (Band::TwoPointFourGhz, 1..=13) => Some(2407 + channel * 5),
+        (Band::FiveGhz, 32..=177) => Some(5000 + channel * 5),
+        (Band::SixGhz, 2) => Some(5935),
+        (Band::SixGhz, 1..=233) if channel % 2 == 1 => Some(5950 + channel * 5),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for (band, channels) in [
+            (Band::TwoPointFourGhz, 1..=14),
+            (Band::FiveGhz, 32..=177),
+            (Band::SixGhz, 1..=233),
+        ] {
+            for channel in channels.filter(|&c| band != Band::SixGhz || c % 2 == 1 || c == 2) {
+                let freq = channel_to_freq(channel, band).unwrap();
+                assert_eq!(freq_to_channel(freq), Some(channel), "{}", freq);
+            }
+        }
+        for invalid in [0, 2407, 2413, 2477, 2483, 5155, 5890, 5940, 5950, 5960, 7120, 5182] {
+            assert_eq!(freq_to_channel(invalid), None, "{}", invalid);
+        }
+        assert_eq!(channel_to_freq(0, Band::TwoPointFourGhz), None);
+        assert_eq!(channel_to_freq(31, Band::FiveGhz), None);
+        assert_eq!(channel_to_freq(4, Band::SixGhz), None);
+        assert_eq!(channel_to_freq(235, Band::SixGhz), None);
+    }
+}
@@ -0,0 +1,118 @@
+#![deny(missing_docs)]
+use std::time::Instant;
+
+/// The time source used to compute timeouts and deadlines
+///
+/// [`SystemClock`] is used unless another clock is given to
+/// [`ClientBuilder::clock`](crate::ClientBuilder::clock) or
+/// [`Client::with_clock`](crate::Client::with_clock). Waiting on the socket
+/// itself still takes real time, so a custom clock is mostly useful with a
+/// transport that never blocks, such as `MockTransport`.
+pub trait Clock: Send {
+    /// The current time
+    fn now(&self) -> Instant;
+}
+
+/// The system's monotonic clock, as given by [`Instant::now`]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+pub use self::mock::MockClock;
+
+#[cfg(any(test, feature = "test-util"))]
+mod mock {
+    use super::Clock;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    #[derive(Clone, Copy, Debug)]
+    struct MockState {
+        now: Instant,
+        tick: Duration,
+    }
+
+    /// A manually driven [`Clock`] for testing timeouts without sleeping
+    ///
+    /// Clones share the same time, so a test can keep one handle while the
+    /// client owns another.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use wpactrl::{Client, MockClock, MockTransport};
+    /// let mock = MockTransport::new();
+    /// mock.push_reply("OK\n");
+    /// let clock = MockClock::ticking(Duration::from_secs(1));
+    /// let mut wpa = Client::from_transport(mock).with_clock(clock).attach().unwrap();
+    /// assert_eq!(wpa.wait_event(Duration::from_secs(30)).unwrap(), None);
+    /// ```
+    #[derive(Clone, Debug)]
+    pub struct MockClock(Arc<Mutex<MockState>>);
+
+    impl MockClock {
+        /// Create a clock that only moves with [`MockClock::advance`]
+        #[must_use]
+        pub fn new() -> Self {
+            Self::ticking(Duration::ZERO)
+        }
+
+        /// Create a clock that also moves forward by `tick` every time it is read
+        ///
+        /// This lets loops that poll until a deadline finish on their own.
+        #[must_use]
+        pub fn ticking(tick: Duration) -> Self {
+            Self(Arc::new(Mutex::new(MockState {
+                now: Instant::now(),
+                tick,
+            })))
+        }
+
+        /// Move the clock forward by `duration`
+        pub fn advance(&self, duration: Duration) {
+            self.0.lock().unwrap().now += duration;
+        }
+    }
+
+    impl Default for MockClock {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> Instant {
+            let mut state = self.0.lock().unwrap();
+            let MockState { now, tick } = *state;
+            state.now = now + tick;
+            now
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn mock_clock() {
+        let clock = MockClock::new();
+        let start = clock.now();
+        assert_eq!(clock.now(), start);
+        clock.clone().advance(Duration::from_secs(3));
+        assert_eq!(clock.now() - start, Duration::from_secs(3));
+
+        let clock = MockClock::ticking(Duration::from_secs(1));
+        let start = clock.now();
+        assert_eq!(clock.now() - start, Duration::from_secs(1));
+        assert_eq!(clock.now() - start, Duration::from_secs(2));
+    }
+}
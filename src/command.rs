@@ -0,0 +1,882 @@
+#![deny(missing_docs)]
+use super::Result;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use crate::bssid::Bssid;
+use crate::error::{Error, ParseError, ParseResult};
+use crate::transport::Transport;
+use crate::wpactrl::{Client, ClientAttached};
+
+/// Debug verbosity of `wpa_supplicant`, as used by the `LOG_LEVEL` command
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogLevel {
+    /// `EXCESSIVE`
+    Excessive,
+    /// `MSGDUMP`
+    MsgDump,
+    /// `DEBUG`
+    Debug,
+    /// `INFO`
+    Info,
+    /// `WARNING`
+    Warning,
+    /// `ERROR`
+    Error,
+}
+
+impl LogLevel {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Excessive => "EXCESSIVE",
+            Self::MsgDump => "MSGDUMP",
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warning => "WARNING",
+            Self::Error => "ERROR",
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> ParseResult<Self> {
+        match s {
+            "EXCESSIVE" => Ok(Self::Excessive),
+            "MSGDUMP" => Ok(Self::MsgDump),
+            "DEBUG" => Ok(Self::Debug),
+            "INFO" => Ok(Self::Info),
+            "WARNING" => Ok(Self::Warning),
+            "ERROR" => Ok(Self::Error),
+            _ => Err(ParseError::new(format!("unknown log level {:?}", s))),
+        }
+    }
+}
+
+/// A global setting changed with `SET`, as opposed to a per-network `SET_NETWORK` parameter
+///
+/// # Examples
+///
+/// ```
+/// use wpactrl::GlobalParam;
+/// assert_eq!(GlobalParam::Country("US".into()).to_string(), "country US");
+/// assert_eq!(GlobalParam::P2pDisabled(true).to_string(), "p2p_disabled 1");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GlobalParam {
+    /// `country`: ISO 3166 country code setting the regulatory domain, eg `US`
+    Country(String),
+    /// `p2p_disabled`: disable Wi-Fi Direct on the interface
+    P2pDisabled(bool),
+    /// `bss_max_count`: maximum number of scanned BSSs kept
+    BssMaxCount(u32),
+    /// `filter_ssids`: only keep scan results for SSIDs of configured networks
+    FilterSsids(bool),
+}
+
+impl fmt::Display for GlobalParam {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Country(alpha2) => write!(f, "country {}", alpha2),
+            Self::P2pDisabled(disabled) => write!(f, "p2p_disabled {}", u8::from(*disabled)),
+            Self::BssMaxCount(count) => write!(f, "bss_max_count {}", count),
+            Self::FilterSsids(filter) => write!(f, "filter_ssids {}", u8::from(*filter)),
+        }
+    }
+}
+
+/// Arguments of the global `INTERFACE_ADD` command
+///
+/// # Examples
+///
+/// ```
+/// use wpactrl::InterfaceAddParams;
+/// let params = InterfaceAddParams {
+///     confname: Some("/etc/wpa_supplicant/wlan1.conf".into()),
+///     driver: Some("nl80211".into()),
+///     ..InterfaceAddParams::new("wlan1")
+/// };
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InterfaceAddParams {
+    /// Name of the network interface
+    pub ifname: String,
+    /// Path of the configuration file
+    pub confname: Option<String>,
+    /// Driver name, eg `nl80211`
+    pub driver: Option<String>,
+    /// Control interface directory (or `DIR=... GROUP=...` parameters)
+    pub ctrl_interface: Option<String>,
+    /// Driver specific parameters
+    pub driver_param: Option<String>,
+    /// Bridge interface name
+    pub bridge_name: Option<String>,
+}
+
+impl InterfaceAddParams {
+    /// Parameters for `ifname`, leaving every other field empty
+    #[must_use]
+    pub fn new<S: Into<String>>(ifname: S) -> Self {
+        Self {
+            ifname: ifname.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Render the tab separated argument list expected by `wpa_supplicant`
+    fn to_args(&self) -> String {
+        [
+            Some(self.ifname.as_str()),
+            self.confname.as_deref(),
+            self.driver.as_deref(),
+            self.ctrl_interface.as_deref(),
+            self.driver_param.as_deref(),
+            self.bridge_name.as_deref(),
+        ]
+        .iter()
+        .map(|field| field.unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\t")
+    }
+}
+
+/// An entry of the PMKSA cache, as reported by the `PMKSA` command
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PmksaEntry {
+    /// Position of the entry in the cache
+    pub index: u32,
+    /// Access point the PMKSA was established with
+    pub bssid: Bssid,
+    /// PMK identifier, hex encoded
+    pub pmkid: String,
+    /// Seconds until the entry expires, negative once expired but not yet purged
+    pub expiration: i64,
+    /// Whether the entry was created by opportunistic key caching
+    pub opportunistic: bool,
+}
+
+impl PmksaEntry {
+    /// Parse the reply of a `PMKSA` command, skipping its header row
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::PmksaEntry;
+    /// let reply = "Index / AA / PMKID / expiration (in seconds) / opportunistic\n\
+    ///              1 00:11:22:33:44:55 000102030405060708090a0b0c0d0e0f 43170 0\n";
+    /// let entries = PmksaEntry::parse_table(reply).unwrap();
+    /// assert_eq!(entries[0].expiration, 43170);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`ParseError`] - Malformed PMKSA entry
+    pub fn parse_table(reply: &str) -> ParseResult<Vec<Self>> {
+        reply
+            .lines()
+            .skip(1)
+            .filter(|line| !line.is_empty())
+            .map(Self::parse)
+            .collect()
+    }
+
+    /// Parse a single space separated row
+    fn parse(line: &str) -> ParseResult<Self> {
+        let invalid = || ParseError::new(format!("invalid PMKSA entry {:?}", line));
+        let mut fields = line.split_whitespace();
+        let mut next = || fields.next().ok_or_else(invalid);
+        Ok(Self {
+            index: next()?.parse().map_err(|_| invalid())?,
+            bssid: next()?.parse()?,
+            pmkid: next()?.to_owned(),
+            expiration: next()?.parse().map_err(|_| invalid())?,
+            opportunistic: match next()? {
+                "0" => false,
+                "1" => true,
+                _ => return Err(invalid()),
+            },
+        })
+    }
+}
+
+/// Fail with [`Error::CommandFailed`] unless `reply` is `OK`
+fn check_ok(cmd: &str, reply: &str) -> Result<()> {
+    if reply == "OK\n" {
+        Ok(())
+    } else {
+        let verb = cmd.split_whitespace().next().unwrap_or_default();
+        Err(Error::CommandFailed(verb.to_owned()))
+    }
+}
+
+impl<T: Transport> ClientAttached<T> {
+    /// Send a command whose only successful reply is `OK`, buffering messages received meanwhile
+    pub(crate) fn request_ok(&mut self, cmd: &str) -> Result<()> {
+        let reply = self.request_raw(cmd)?;
+        check_ok(cmd, &reply)
+    }
+}
+
+impl<T: Transport> Client<T> {
+    /// Send a command whose only successful reply is `OK`
+    pub(crate) fn request_ok(&mut self, cmd: &str) -> Result<()> {
+        let reply = self.request_raw(cmd)?;
+        check_ok(cmd, &reply)
+    }
+
+    /// Query the current debug verbosity of `wpa_supplicant`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// println!("{}", wpa.log_level().unwrap());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Parse`] - Reply did not contain a known log level
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn log_level(&mut self) -> Result<LogLevel> {
+        let reply = self.request_raw("LOG_LEVEL")?;
+        let level = reply
+            .lines()
+            .find_map(|line| line.strip_prefix("Current level: "))
+            .ok_or_else(|| ParseError::new("missing current log level"))?
+            .trim()
+            .parse()?;
+        Ok(level)
+    }
+
+    /// Change the debug verbosity of `wpa_supplicant`, optionally enabling timestamps
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::LogLevel;
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.set_log_level(LogLevel::Info, false).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (non-OK) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn set_log_level(&mut self, level: LogLevel, timestamp: bool) -> Result<()> {
+        self.request_ok(&format!("LOG_LEVEL {} {}", level, u8::from(timestamp)))
+    }
+
+    /// Change a global setting of `wpa_supplicant` with `SET`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::GlobalParam;
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.set_global(GlobalParam::Country("DE".into())).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (non-OK) response, eg an invalid value
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn set_global(&mut self, param: GlobalParam) -> Result<()> {
+        self.request_ok(&format!("SET {}", param))
+    }
+
+    /// Ask the global control interface to start managing a network interface
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use wpactrl::InterfaceAddParams;
+    /// let mut wpa = wpactrl::Client::builder()
+    ///             .ctrl_path("/var/run/wpa_supplicant-global")
+    ///             .open()
+    ///             .unwrap();
+    /// let params = InterfaceAddParams {
+    ///     confname: Some("/etc/wpa_supplicant/wlan1.conf".into()),
+    ///     ..InterfaceAddParams::new("wlan1")
+    /// };
+    /// wpa.interface_add(&params).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (non-OK) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn interface_add(&mut self, params: &InterfaceAddParams) -> Result<()> {
+        self.request_ok(&format!("INTERFACE_ADD {}", params.to_args()))
+    }
+
+    /// Ask the global control interface to stop managing a network interface
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let mut wpa = wpactrl::Client::builder()
+    ///             .ctrl_path("/var/run/wpa_supplicant-global")
+    ///             .open()
+    ///             .unwrap();
+    /// wpa.interface_remove("wlan1").unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (non-OK) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn interface_remove(&mut self, ifname: &str) -> Result<()> {
+        self.request_ok(&format!("INTERFACE_REMOVE {}", ifname))
+    }
+
+    /// Remove every configured network in a single command
+    ///
+    /// This uses `REMOVE_NETWORK all`, so it does not race with networks being
+    /// added or removed concurrently the way listing and removing each id would.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.remove_all_networks().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (non-OK) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn remove_all_networks(&mut self) -> Result<()> {
+        self.request_ok("REMOVE_NETWORK all")
+    }
+
+    /// Abort an in-progress WPS session
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.wps_cancel().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (non-OK) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn wps_cancel(&mut self) -> Result<()> {
+        self.request_ok("WPS_CANCEL")
+    }
+
+    /// List the entries of the PMKSA cache
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// for entry in wpa.pmksa().unwrap() {
+    ///     println!("{} expires in {}s", entry.bssid, entry.expiration);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (`FAIL`) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Parse`] - Malformed PMKSA entry
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn pmksa(&mut self) -> Result<Vec<PmksaEntry>> {
+        let reply = self.request_raw("PMKSA")?;
+        if reply.starts_with("FAIL") {
+            return Err(Error::CommandFailed("PMKSA".into()));
+        }
+        Ok(PmksaEntry::parse_table(&reply)?)
+    }
+
+    /// Remove every entry of the PMKSA cache
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.pmksa_flush().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (non-OK) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn pmksa_flush(&mut self) -> Result<()> {
+        self.request_ok("PMKSA_FLUSH")
+    }
+
+    /// Move to another access point of the current network
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// let ssid = "home";
+    /// if let Some(best) = wpa.best_bss_for_ssid(ssid).unwrap() {
+    ///     wpa.roam(best.bssid).unwrap();
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unknown access point, or not part of the current network
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn roam(&mut self, bssid: Bssid) -> Result<()> {
+        self.request_ok(&format!("ROAM {}", bssid))
+    }
+
+    /// List the access points `wpa_supplicant` currently avoids
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// for bssid in wpa.blacklist().unwrap() {
+    ///     println!("{}", bssid);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (FAIL) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Parse`] - Malformed BSSID in the reply
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn blacklist(&mut self) -> Result<Vec<Bssid>> {
+        let reply = self.request_raw("BLACKLIST")?;
+        if reply.starts_with("FAIL") {
+            return Err(Error::CommandFailed("BLACKLIST".into()));
+        }
+        Ok(reply.lines().filter(|line| !line.is_empty()).map(str::parse).collect::<ParseResult<_>>()?)
+    }
+
+    /// Make `wpa_supplicant` avoid an access point
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::Bssid;
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.blacklist_add(Bssid::from([0x00, 0x11, 0x22, 0x33, 0x44, 0x55])).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (non-OK) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn blacklist_add(&mut self, bssid: Bssid) -> Result<()> {
+        self.request_ok(&format!("BLACKLIST {}", bssid))
+    }
+
+    /// Empty the list of access points `wpa_supplicant` avoids
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.blacklist_clear().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (non-OK) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn blacklist_clear(&mut self) -> Result<()> {
+        self.request_ok("BLACKLIST clear")
+    }
+
+    /// Inject an EAPOL frame as if it had been received from `src`
+    ///
+    /// This is meant for 802.1X test suites, and requires `wpa_supplicant` /
+    /// `hostapd` to be built with testing support.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::Bssid;
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// let src = Bssid::from([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]);
+    /// wpa.eapol_rx(src, &[0x02, 0x00, 0x00, 0x04, 0x01, 0x01, 0x00, 0x04]).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Frame rejected, eg no matching station
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn eapol_rx(&mut self, src: Bssid, data: &[u8]) -> Result<()> {
+        let mut cmd = format!("EAPOL_RX {} ", src);
+        for byte in data {
+            // Writing to a String cannot fail
+            let _ = write!(cmd, "{:02x}", byte);
+        }
+        self.request_ok(&cmd)
+    }
+
+    /// Write a marker into the debug log of `wpa_supplicant`
+    ///
+    /// This helps correlating application actions with the supplicant logs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.note("starting roaming test").unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (non-OK) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn note(&mut self, text: &str) -> Result<()> {
+        self.request_ok(&format!("NOTE {}", text))
+    }
+
+    /// Make `wpa_supplicant` re-read its configuration file
+    ///
+    /// This applies changes made to the file outside of the control interface.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.reconfigure().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - The configuration could not be loaded, eg because of a syntax error
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn reconfigure(&mut self) -> Result<()> {
+        self.request_ok("RECONFIGURE")
+    }
+
+    /// List what the driver supports for a capability field
+    ///
+    /// Common fields are `eap`, `pairwise`, `group`, `key_mgmt`, `proto`,
+    /// `auth_alg` and `modes`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// let key_mgmt = wpa.get_capability("key_mgmt").unwrap();
+    /// println!("SAE supported: {}", key_mgmt.iter().any(|k| k == "SAE"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unknown capability field
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn get_capability(&mut self, capability: &str) -> Result<Vec<String>> {
+        let reply = self.request_raw(&format!("GET_CAPABILITY {}", capability))?;
+        if reply.starts_with("FAIL") {
+            return Err(Error::CommandFailed("GET_CAPABILITY".into()));
+        }
+        Ok(reply.split_whitespace().map(str::to_owned).collect())
+    }
+
+    /// List the commands supported by `wpa_supplicant` / `hostapd`
+    ///
+    /// Only the command names are returned, without their arguments or descriptions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// match wpa.help() {
+    ///     Ok(commands) => println!("{}", commands.join(" ")),
+    ///     Err(wpactrl::Error::UnknownCommand(_)) => println!("HELP is not supported"),
+    ///     Err(e) => panic!("{}", e),
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (FAIL) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::UnknownCommand`] - `HELP` is not supported
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn help(&mut self) -> Result<Vec<String>> {
+        let reply = self.request_raw("HELP")?;
+        if reply.starts_with("UNKNOWN COMMAND") {
+            return Err(Error::UnknownCommand("HELP".into()));
+        }
+        if reply.starts_with("FAIL") {
+            return Err(Error::CommandFailed("HELP".into()));
+        }
+        Ok(reply
+            .lines()
+            // Description lines wrapping onto the next line are indented
+            .filter(|line| !line.starts_with(char::is_whitespace))
+            .filter_map(|line| line.split_whitespace().next())
+            .map(str::to_owned)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transport::mock_ctrl;
+
+    #[test]
+    fn log_level() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("Current level: MSGDUMP\nTimestamp: 0\n");
+        assert_eq!(wpa.log_level().unwrap(), LogLevel::MsgDump);
+        mock.push_reply("FAIL\n");
+        assert!(matches!(wpa.log_level(), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn set_log_level() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        wpa.set_log_level(LogLevel::Debug, true).unwrap();
+        mock.push_reply("FAIL\n");
+        assert!(matches!(
+            wpa.set_log_level(LogLevel::Error, false),
+            Err(Error::CommandFailed(ref cmd)) if cmd == "LOG_LEVEL"
+        ));
+        assert_eq!(mock.sent(), vec!["LOG_LEVEL DEBUG 1", "LOG_LEVEL ERROR 0"]);
+    }
+
+    #[test]
+    fn set_global() {
+        let (mock, mut wpa) = mock_ctrl();
+        for _ in 0..3 {
+            mock.push_reply("OK\n");
+        }
+        mock.push_reply("FAIL\n");
+        wpa.set_global(GlobalParam::Country("US".into())).unwrap();
+        wpa.set_global(GlobalParam::P2pDisabled(true)).unwrap();
+        wpa.set_global(GlobalParam::BssMaxCount(200)).unwrap();
+        assert!(matches!(
+            wpa.set_global(GlobalParam::FilterSsids(false)),
+            Err(Error::CommandFailed(ref cmd)) if cmd == "SET"
+        ));
+        assert_eq!(
+            mock.sent(),
+            vec![
+                "SET country US",
+                "SET p2p_disabled 1",
+                "SET bss_max_count 200",
+                "SET filter_ssids 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn interface_add() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        mock.push_reply("FAIL\n");
+        let params = InterfaceAddParams {
+            driver: Some("nl80211".into()),
+            bridge_name: Some("br0".into()),
+            ..InterfaceAddParams::new("wlan1")
+        };
+        wpa.interface_add(&params).unwrap();
+        assert!(matches!(wpa.interface_remove("wlan1"), Err(Error::CommandFailed(_))));
+        assert_eq!(
+            mock.sent(),
+            vec!["INTERFACE_ADD wlan1\t\tnl80211\t\t\tbr0", "INTERFACE_REMOVE wlan1"]
+        );
+    }
+
+    #[test]
+    fn get_capability() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("CCMP TKIP NONE\n");
+        mock.push_reply("FAIL\n");
+        assert_eq!(wpa.get_capability("pairwise").unwrap(), vec!["CCMP", "TKIP", "NONE"]);
+        assert!(matches!(wpa.get_capability("bogus"), Err(Error::CommandFailed(_))));
+        assert_eq!(mock.sent(), vec!["GET_CAPABILITY pairwise", "GET_CAPABILITY bogus"]);
+    }
+
+    #[test]
+    fn remove_all_networks() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        wpa.remove_all_networks().unwrap();
+        assert_eq!(mock.sent(), vec!["REMOVE_NETWORK all"]);
+    }
+
+    #[test]
+    fn wps_cancel() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        mock.push_reply("FAIL\n");
+        wpa.wps_cancel().unwrap();
+        assert!(matches!(wpa.wps_cancel(), Err(Error::CommandFailed(ref cmd)) if cmd == "WPS_CANCEL"));
+        assert_eq!(mock.sent(), vec!["WPS_CANCEL", "WPS_CANCEL"]);
+    }
+
+    #[test]
+    fn pmksa() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply(
+            "Index / AA / PMKID / expiration (in seconds) / opportunistic\n\
+             1 00:11:22:33:44:55 000102030405060708090a0b0c0d0e0f 43170 0\n\
+             2 66:77:88:99:aa:bb 101112131415161718191a1b1c1d1e1f 120 1\n\
+             3 00:11:22:33:44:66 202122232425262728292a2b2c2d2e2f -5 0\n",
+        );
+        mock.push_reply("OK\n");
+        let entries = wpa.pmksa().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[2].expiration, -5);
+        assert_eq!(entries[1].bssid.to_string(), "66:77:88:99:aa:bb");
+        assert_eq!(entries[1].pmkid, "101112131415161718191a1b1c1d1e1f");
+        assert_eq!(entries[1].expiration, 120);
+        assert!(entries[1].opportunistic);
+        wpa.pmksa_flush().unwrap();
+        assert_eq!(mock.sent(), vec!["PMKSA", "PMKSA_FLUSH"]);
+        assert!(PmksaEntry::parse_table("header\n1 00:11:22:33:44:55 00 soon 0\n").is_err());
+    }
+
+    #[test]
+    fn reconfigure() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("FAIL\n");
+        assert!(matches!(wpa.reconfigure(), Err(Error::CommandFailed(ref cmd)) if cmd == "RECONFIGURE"));
+        assert_eq!(mock.sent(), vec!["RECONFIGURE"]);
+    }
+
+    #[test]
+    fn blacklist() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("00:11:22:33:44:55\n66:77:88:99:aa:bb\n");
+        mock.push_reply("");
+        mock.push_reply("OK\n");
+        mock.push_reply("OK\n");
+        let bssid = Bssid::from([0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb]);
+        assert_eq!(wpa.blacklist().unwrap()[1], bssid);
+        assert!(wpa.blacklist().unwrap().is_empty());
+        wpa.blacklist_add(bssid).unwrap();
+        wpa.blacklist_clear().unwrap();
+        assert_eq!(
+            mock.sent(),
+            vec!["BLACKLIST", "BLACKLIST", "BLACKLIST 66:77:88:99:aa:bb", "BLACKLIST clear"]
+        );
+    }
+
+    #[test]
+    fn eapol_rx() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let src = Bssid::from([0x02, 0x00, 0x00, 0x00, 0x0a, 0x01]);
+        wpa.eapol_rx(src, &[0x02, 0x00, 0xff, 0x0a]).unwrap();
+        assert_eq!(mock.sent(), vec!["EAPOL_RX 02:00:00:00:0a:01 0200ff0a"]);
+    }
+
+    #[test]
+    fn note() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        wpa.note("step 1: connect").unwrap();
+        assert_eq!(mock.sent(), vec!["NOTE step 1: connect"]);
+    }
+
+    #[test]
+    fn roam() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        mock.push_reply("FAIL\n");
+        let bssid = Bssid::from([0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb]);
+        wpa.roam(bssid).unwrap();
+        assert!(matches!(wpa.roam(bssid), Err(Error::CommandFailed(ref cmd)) if cmd == "ROAM"));
+        assert_eq!(mock.sent(), vec!["ROAM 66:77:88:99:aa:bb", "ROAM 66:77:88:99:aa:bb"]);
+    }
+
+    #[test]
+    fn help() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply(
+            "PING\nSTATUS [verbose]   get current status\n\nSCAN\n\
+             SET_NETWORK <id> <variable> <value>   set network variables\n\
+             \x20 (shows list of variables when run without arguments)\n",
+        );
+        mock.push_reply("UNKNOWN COMMAND\n");
+        assert_eq!(wpa.help().unwrap(), vec!["PING", "STATUS", "SCAN", "SET_NETWORK"]);
+        assert!(matches!(wpa.help(), Err(Error::UnknownCommand(ref cmd)) if cmd == "HELP"));
+    }
+}
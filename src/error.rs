@@ -1,7 +1,32 @@
 #![deny(missing_docs)]
 
+use std::path::PathBuf;
 use std::{io, str};
 
+/// A reply or message from wpasupplicant that could not be interpreted
+///
+/// Unlike [`Error`], this does not depend on `std::io`, so the parsers that
+/// return it can be used on bytes obtained without a control socket.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError(String);
+
+impl ParseError {
+    pub(crate) fn new<S: Into<String>>(what: S) -> Self {
+        Self(what.into())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A `Result` alias for the parsers, where the `Err` case is [`ParseError`]
+pub(crate) type ParseResult<T> = std::result::Result<T, ParseError>;
+
 /// The errors that may occur using `wpactrl`
 #[derive(Debug)]
 pub enum Error {
@@ -17,16 +42,71 @@ pub enum Error {
     /// Represents a failed `DETACH` request to wpasupplicant.
     Detach,
 
-    /// Error waiting for a response
-    Wait
+    /// Error waiting for a response, with the OS error reported by `select`
+    Wait(io::Error),
+
+    /// Represents a wait that ran past its configured timeout.
+    Timeout,
+
+    /// Represents a command that wpasupplicant answered with something other than `OK`.
+    CommandFailed(String),
+
+    /// Represents a reply from wpasupplicant that could not be interpreted.
+    Parse(ParseError),
+
+    /// Represents a control socket path where nothing exists.
+    CtrlPathMissing(PathBuf),
+
+    /// Represents a control socket path that exists but is not a socket.
+    CtrlPathNotSocket(PathBuf),
+
+    /// Represents a control socket directory holding several candidate sockets.
+    AmbiguousInterface(Vec<PathBuf>),
+
+    /// Represents a control socket path this process is not allowed to access.
+    PermissionDenied(PathBuf),
+
+    /// Represents a command that wpasupplicant does not implement.
+    UnknownCommand(String),
+
+    /// Represents a message that did not fit in the receive buffer and was cut short.
+    Truncated,
+
+    /// Represents a `SCAN` refused because a scan is already running.
+    ScanBusy,
+
+    /// Represents an access point rejecting association, with the 802.11 status code.
+    AssocRejected(u16),
+
+    /// Represents a network whose SSID was not found by scanning.
+    NetworkNotFound(String),
+
+    /// Represents repeated authentication failures, usually caused by a wrong password.
+    WrongKey,
+
+    /// Represents a socket path that cannot be used, with the reason why.
+    InvalidPath(PathBuf, &'static str),
+
+    /// Represents a datagram from an unexpected user, with the sender's user ID if known.
+    UntrustedPeer(Option<u32>),
+
+    /// Represents a request to verify the sender of datagrams over a transport that does not identify it.
+    CredentialsUnavailable,
 }
 
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match *self {
-            Self::Attach|Self::Detach|Self::Wait => None,
-            Self::Io(ref source) => Some(source),
+            Self::Attach|Self::Detach|Self::Timeout|Self::CommandFailed(_) => None,
+            Self::UnknownCommand(_) => None,
+            Self::AssocRejected(_)|Self::ScanBusy|Self::Truncated => None,
+            Self::CtrlPathMissing(_)|Self::CtrlPathNotSocket(_)|Self::AmbiguousInterface(_) => None,
+            Self::PermissionDenied(_)|Self::InvalidPath(..)|Self::UntrustedPeer(_) => None,
+            Self::CredentialsUnavailable => None,
+            Self::NetworkNotFound(_)|Self::WrongKey => None,
+            Self::Io(ref source)|Self::Wait(ref source) => Some(source),
             Self::Utf8ToStr(ref source) => Some(source),
+            Self::Parse(ref source) => Some(source),
         }
     }
 }
@@ -40,8 +120,64 @@ impl std::fmt::Display for Error {
             Self::Detach => {
                 write!(f, "Failed to detach from wpasupplicant")
             }
-            Self::Wait => {
-                write!(f, "Unable to wait for response from wpasupplicant")
+            Self::Wait(ref err) => {
+                write!(f, "Unable to wait for response from wpasupplicant: {}", err)
+            }
+            Self::Timeout => {
+                write!(f, "Timed out waiting for wpasupplicant")
+            }
+            Self::CommandFailed(ref cmd) => {
+                write!(f, "wpasupplicant rejected the {} command", cmd)
+            }
+            Self::Parse(ref what) => {
+                write!(f, "Failed to parse reply from wpasupplicant: {}", what)
+            }
+            Self::CtrlPathMissing(ref path) => {
+                write!(f, "No wpasupplicant control socket at {}", path.display())
+            }
+            Self::CtrlPathNotSocket(ref path) => {
+                write!(f, "{} is not a wpasupplicant control socket", path.display())
+            }
+            Self::AmbiguousInterface(ref sockets) => {
+                let sockets: Vec<_> = sockets.iter().map(|s| s.display().to_string()).collect();
+                write!(f, "Several wpasupplicant control sockets found: {}", sockets.join(", "))
+            }
+            Self::PermissionDenied(ref path) => {
+                write!(
+                    f,
+                    "Permission denied on wpasupplicant control socket {}: run with elevated privileges (eg as root) or join the group owning it",
+                    path.display()
+                )
+            }
+            Self::UnknownCommand(ref cmd) => {
+                write!(f, "wpasupplicant does not support the {} command", cmd)
+            }
+            Self::Truncated => {
+                write!(f, "Message from wpasupplicant was too long and got truncated")
+            }
+            Self::ScanBusy => {
+                write!(f, "wpasupplicant is already scanning")
+            }
+            Self::AssocRejected(status_code) => {
+                write!(f, "Association rejected with status code {}", status_code)
+            }
+            Self::NetworkNotFound(ref ssid) => {
+                write!(f, "No access point found for network {:?}", ssid)
+            }
+            Self::WrongKey => {
+                write!(f, "Authentication failed repeatedly, the password is likely wrong")
+            }
+            Self::InvalidPath(ref path, reason) => {
+                write!(f, "Invalid socket path {}: {}", path.display(), reason)
+            }
+            Self::UntrustedPeer(Some(uid)) => {
+                write!(f, "Rejected a message sent by untrusted user {}", uid)
+            }
+            Self::UntrustedPeer(None) => {
+                write!(f, "Rejected a message sent without credentials")
+            }
+            Self::CredentialsUnavailable => {
+                write!(f, "Cannot verify the sender of messages over this transport")
             }
             Self::Io(ref err) => {
                 write!(f, "Failed to execute the specified command: {}", err)
@@ -59,6 +195,12 @@ impl From<std::io::Error> for Error {
     }
 }
 
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Self::Parse(err)
+    }
+}
+
 impl From<str::Utf8Error> for Error {
     fn from(err: str::Utf8Error) -> Self {
         Self::Utf8ToStr(err)
@@ -0,0 +1,675 @@
+#![deny(missing_docs)]
+use std::fmt;
+
+use crate::bssid::Bssid;
+
+/// IEEE 802.11 reason code carried by deauthentication and disassociation frames
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DisconnectReason {
+    /// 1: Unspecified reason
+    Unspecified,
+    /// 2: Previous authentication no longer valid
+    PrevAuthNotValid,
+    /// 3: Deauthenticated because the station is leaving
+    Deauth,
+    /// 4: Disassociated due to inactivity
+    Inactivity,
+    /// 5: Disassociated because the access point cannot handle all associated stations
+    ApBusy,
+    /// 6: Class 2 frame received from a nonauthenticated station
+    Class2FrameFromNonauthSta,
+    /// 7: Class 3 frame received from a nonassociated station
+    Class3FrameFromNonassocSta,
+    /// 8: Disassociated because the station is leaving
+    Disassoc,
+    /// 9: Station requesting association is not authenticated
+    StaReqAssocWithoutAuth,
+    /// 10: Power capability element is unacceptable
+    PwrCapabilityNotValid,
+    /// 11: Supported channels element is unacceptable
+    SupportedChannelNotValid,
+    /// 12: Disassociated due to BSS transition management
+    BssTransitionDisassoc,
+    /// 13: Invalid element
+    InvalidIe,
+    /// 14: Message integrity code failure
+    MichaelMicFailure,
+    /// 15: 4-way handshake timeout
+    FourwayHandshakeTimeout,
+    /// 16: Group key handshake timeout
+    GroupKeyUpdateTimeout,
+    /// 17: Element in the 4-way handshake differs from the association request
+    IeIn4wayDiffers,
+    /// 18: Invalid group cipher
+    GroupCipherNotValid,
+    /// 19: Invalid pairwise cipher
+    PairwiseCipherNotValid,
+    /// 20: Invalid AKMP
+    AkmpNotValid,
+    /// 21: Unsupported RSNE version
+    UnsupportedRsnIeVersion,
+    /// 22: Invalid RSNE capabilities
+    InvalidRsnIeCapab,
+    /// 23: IEEE 802.1X authentication failed
+    Ieee8021xAuthFailed,
+    /// 24: Cipher suite rejected because of the security policy
+    CipherSuiteRejected,
+    /// 34: Disassociated because of excessive unacknowledged frames
+    DisassocLowAck,
+    /// Any other reason code
+    Other(u16),
+}
+
+impl DisconnectReason {
+    /// The numeric reason code
+    #[must_use]
+    pub fn code(self) -> u16 {
+        match self {
+            Self::Unspecified => 1,
+            Self::PrevAuthNotValid => 2,
+            Self::Deauth => 3,
+            Self::Inactivity => 4,
+            Self::ApBusy => 5,
+            Self::Class2FrameFromNonauthSta => 6,
+            Self::Class3FrameFromNonassocSta => 7,
+            Self::Disassoc => 8,
+            Self::StaReqAssocWithoutAuth => 9,
+            Self::PwrCapabilityNotValid => 10,
+            Self::SupportedChannelNotValid => 11,
+            Self::BssTransitionDisassoc => 12,
+            Self::InvalidIe => 13,
+            Self::MichaelMicFailure => 14,
+            Self::FourwayHandshakeTimeout => 15,
+            Self::GroupKeyUpdateTimeout => 16,
+            Self::IeIn4wayDiffers => 17,
+            Self::GroupCipherNotValid => 18,
+            Self::PairwiseCipherNotValid => 19,
+            Self::AkmpNotValid => 20,
+            Self::UnsupportedRsnIeVersion => 21,
+            Self::InvalidRsnIeCapab => 22,
+            Self::Ieee8021xAuthFailed => 23,
+            Self::CipherSuiteRejected => 24,
+            Self::DisassocLowAck => 34,
+            Self::Other(code) => code,
+        }
+    }
+}
+
+impl From<u16> for DisconnectReason {
+    fn from(code: u16) -> Self {
+        match code {
+            1 => Self::Unspecified,
+            2 => Self::PrevAuthNotValid,
+            3 => Self::Deauth,
+            4 => Self::Inactivity,
+            5 => Self::ApBusy,
+            6 => Self::Class2FrameFromNonauthSta,
+            7 => Self::Class3FrameFromNonassocSta,
+            8 => Self::Disassoc,
+            9 => Self::StaReqAssocWithoutAuth,
+            10 => Self::PwrCapabilityNotValid,
+            11 => Self::SupportedChannelNotValid,
+            12 => Self::BssTransitionDisassoc,
+            13 => Self::InvalidIe,
+            14 => Self::MichaelMicFailure,
+            15 => Self::FourwayHandshakeTimeout,
+            16 => Self::GroupKeyUpdateTimeout,
+            17 => Self::IeIn4wayDiffers,
+            18 => Self::GroupCipherNotValid,
+            19 => Self::PairwiseCipherNotValid,
+            20 => Self::AkmpNotValid,
+            21 => Self::UnsupportedRsnIeVersion,
+            22 => Self::InvalidRsnIeCapab,
+            23 => Self::Ieee8021xAuthFailed,
+            24 => Self::CipherSuiteRejected,
+            34 => Self::DisassocLowAck,
+            code => Self::Other(code),
+        }
+    }
+}
+
+impl From<DisconnectReason> for u16 {
+    fn from(reason: DisconnectReason) -> Self {
+        reason.code()
+    }
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Other(code) => write!(f, "reason {}", code),
+            reason => write!(f, "{:?} ({})", reason, reason.code()),
+        }
+    }
+}
+
+/// A control interface message, as received by [`ClientAttached::recv`](crate::ClientAttached::recv)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// `CTRL-EVENT-CONNECTED`: the station is associated and authenticated
+    Connected {
+        /// Access point the station connected to
+        bssid: Bssid,
+        /// Identifier of the configured network, if reported
+        id: Option<u32>,
+        /// The `id_str` of the configured network, if reported and not empty
+        id_str: Option<String>,
+    },
+    /// `CTRL-EVENT-DISCONNECTED`
+    Disconnected {
+        /// Access point the station was connected to
+        bssid: Bssid,
+        /// Why the connection ended
+        reason: DisconnectReason,
+        /// Whether the disconnection was initiated by this station
+        locally_generated: bool,
+    },
+    /// `CTRL-EVENT-ASSOC-REJECT`: the access point refused association
+    AssocReject {
+        /// Access point that refused, if known
+        bssid: Option<Bssid>,
+        /// IEEE 802.11 status code, eg 17 when the access point is full
+        status_code: u16,
+    },
+    /// `WPS-SUCCESS`: the WPS session provisioned credentials
+    WpsSuccess,
+    /// `WPS-FAIL`: the WPS session failed
+    WpsFail {
+        /// The WPS message type during which the failure occurred
+        msg: Option<u8>,
+        /// The WPS configuration error reported by the peer, 0 if none
+        config_error: Option<u16>,
+    },
+    /// `WPS-TIMEOUT`: no WPS session completed within the walk time
+    WpsTimeout,
+    /// `WPS-OVERLAP-DETECTED`: several access points are in push button mode
+    ///
+    /// The session is aborted, and the user should retry once only one access
+    /// point has its button pressed.
+    WpsOverlapDetected,
+    /// The access point announced it will disassociate this station
+    ///
+    /// Reported as `CTRL-EVENT-DISASSOC-IMMINENT disassoc_timer=N` or
+    /// `WNM: Disassociation Imminent - Disassociation Timer N`.
+    DisassocImminent {
+        /// Beacon intervals left before disassociation, 0 if unspecified
+        disassoc_timer: u32,
+    },
+    /// `BSS-TM-RESP`: a station answered a BSS transition management request
+    BssTmResp {
+        /// Station that answered
+        addr: Bssid,
+        /// Token of the request being answered
+        dialog_token: u8,
+        /// BSS transition status code, 0 when the station accepted
+        status_code: u8,
+        /// Access point the station is moving to, if given
+        target_bssid: Option<Bssid>,
+    },
+    /// `CTRL-EVENT-REGDOM-CHANGE`: the regulatory domain changed
+    ///
+    /// This affects the available channels and transmit power.
+    RegdomChange {
+        /// ISO 3166 country code, eg `US`, or empty if not reported
+        alpha2: String,
+        /// What initiated the change, eg `CORE`, `USER`, `DRIVER` or `COUNTRY_IE`
+        init: String,
+    },
+    /// `CTRL-EVENT-SSID-TEMP-DISABLED`: connection attempts to a network are paused after failures
+    SsidTempDisabled {
+        /// Network identifier, as used by `SELECT_NETWORK`
+        id: u32,
+        /// Network name, with non-printable characters escaped as by `wpa_supplicant`
+        ssid: String,
+        /// Consecutive authentication failures so far
+        auth_failures: u32,
+        /// Seconds before the network is tried again
+        duration: u32,
+        /// Why the last attempt failed, eg `WRONG_KEY` or `CONN_FAILED`, if reported
+        reason: Option<String>,
+    },
+    /// `CTRL-EVENT-SSID-REENABLED`: a temporarily disabled network may be tried again
+    SsidReenabled {
+        /// Network identifier, as used by `SELECT_NETWORK`
+        id: u32,
+        /// Network name, with non-printable characters escaped as by `wpa_supplicant`
+        ssid: String,
+    },
+    /// `CTRL-EVENT-SIGNAL-CHANGE`: the signal crossed the threshold set with
+    /// [`Client::set_signal_threshold`](crate::Client::set_signal_threshold)
+    SignalChange {
+        /// Whether the signal is now above the threshold
+        above: bool,
+        /// Signal strength in dBm
+        signal: i32,
+        /// Noise level in dBm, if the driver reports it
+        noise: Option<i32>,
+        /// Transmit rate in kbit/s, if reported
+        txrate: Option<u32>,
+    },
+    /// `CTRL-EVENT-SAE-*`: a WPA3-SAE authentication event, eg `CTRL-EVENT-SAE-UNKNOWN-PASSWORD-IDENTIFIER`
+    Sae {
+        /// The part of the event name after `CTRL-EVENT-SAE-`
+        kind: String,
+        /// Peer address, if reported
+        addr: Option<Bssid>,
+    },
+    /// Any message without a dedicated variant, with its `<N>` priority prefix removed
+    Other(String),
+}
+
+/// The value of the first `key=value` field named `key` in `fields`
+fn field<'a>(fields: &'a str, key: &str) -> Option<&'a str> {
+    fields
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix(key)?.strip_prefix('='))
+}
+
+/// The value of the `key="value"` field in `fields`, and the fields after it
+///
+/// The value may contain spaces and `\"`-escaped quotes, which are kept as is.
+fn quoted_field<'a>(fields: &'a str, key: &str) -> Option<(&'a str, &'a str)> {
+    let start = fields.find(&format!("{}=\"", key))? + key.len() + 2;
+    let value = &fields[start..];
+    let mut escaped = false;
+    let end = value.char_indices().find_map(|(i, c)| match c {
+        '"' if !escaped => Some(i),
+        '\\' => {
+            escaped = !escaped;
+            None
+        }
+        _ => {
+            escaped = false;
+            None
+        }
+    })?;
+    Some((&value[..end], &value[end + 1..]))
+}
+
+/// Whether `msg` starts with a well-formed `<N>` priority prefix, as all events do
+///
+/// Anything else, even starting with `<`, is a command reply.
+pub(crate) fn is_event(msg: &[u8]) -> bool {
+    msg.strip_prefix(b"<")
+        .and_then(|rest| rest.iter().position(|&b| b == b'>'))
+        .is_some_and(|end| end > 0 && msg[1..=end].iter().all(u8::is_ascii_digit))
+}
+
+/// The level in the `<N>` priority prefix of an event message
+pub(crate) fn priority(msg: &[u8]) -> Option<u8> {
+    let rest = msg.strip_prefix(b"<")?;
+    let end = rest.iter().position(|&b| b == b'>')?;
+    if end == 0 || !rest[..end].iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    std::str::from_utf8(&rest[..end]).ok()?.parse().ok()
+}
+
+/// `msg` without its `<N>` priority prefix, if any
+pub(crate) fn strip_priority(msg: &str) -> &str {
+    msg.strip_prefix('<')
+        .and_then(|rest| rest.split_once('>'))
+        .filter(|(priority, _)| {
+            !priority.is_empty() && priority.bytes().all(|b| b.is_ascii_digit())
+        })
+        .map_or(msg, |(_, body)| body)
+}
+
+impl Event {
+    /// Interpret a control interface message, with or without its `<N>` priority prefix
+    ///
+    /// Messages that are not recognized, or whose fields are malformed, are
+    /// returned as [`Event::Other`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::{DisconnectReason, Event};
+    /// let event = Event::parse("<3>CTRL-EVENT-DISCONNECTED bssid=00:11:22:33:44:55 reason=3");
+    /// assert!(matches!(event, Event::Disconnected { reason: DisconnectReason::Deauth, .. }));
+    /// ```
+    #[must_use]
+    pub fn parse(msg: &str) -> Self {
+        let body = strip_priority(msg);
+        let (name, fields) = body.split_once(' ').unwrap_or((body, ""));
+        let event = match name {
+            "CTRL-EVENT-CONNECTED" => Self::parse_connected(fields),
+            "CTRL-EVENT-DISCONNECTED" => Self::parse_disconnected(fields),
+            "CTRL-EVENT-ASSOC-REJECT" => Self::parse_assoc_reject(fields),
+            "WPS-SUCCESS" => Some(Self::WpsSuccess),
+            "WPS-FAIL" => Some(Self::WpsFail {
+                msg: field(fields, "msg").and_then(|msg| msg.parse().ok()),
+                config_error: field(fields, "config_error").and_then(|e| e.parse().ok()),
+            }),
+            "WPS-TIMEOUT" => Some(Self::WpsTimeout),
+            "WPS-OVERLAP-DETECTED" => Some(Self::WpsOverlapDetected),
+            "CTRL-EVENT-DISASSOC-IMMINENT" => field(fields, "disassoc_timer")
+                .map_or(Some(0), |timer| timer.parse().ok())
+                .map(|disassoc_timer| Self::DisassocImminent { disassoc_timer }),
+            "BSS-TM-RESP" => Self::parse_bss_tm_resp(fields),
+            "CTRL-EVENT-SSID-TEMP-DISABLED" => Self::parse_ssid_temp_disabled(fields),
+            "CTRL-EVENT-SSID-REENABLED" => Self::parse_ssid_reenabled(fields),
+            "CTRL-EVENT-REGDOM-CHANGE" => field(fields, "init").map(|init| Self::RegdomChange {
+                alpha2: field(fields, "alpha2").unwrap_or_default().to_owned(),
+                init: init.to_owned(),
+            }),
+            "CTRL-EVENT-SIGNAL-CHANGE" => Self::parse_signal_change(fields),
+            "WNM:" => Self::parse_wnm(fields),
+            _ => name.strip_prefix("CTRL-EVENT-SAE-").map(|kind| Self::Sae {
+                kind: kind.to_owned(),
+                addr: fields
+                    .split_whitespace()
+                    .next()
+                    .and_then(|addr| addr.parse().ok()),
+            }),
+        };
+        event.unwrap_or_else(|| Self::Other(body.to_owned()))
+    }
+
+    /// Parse `- Connection to <bssid> completed [id=0 id_str=]`, the bracketed part being optional
+    fn parse_connected(fields: &str) -> Option<Self> {
+        let (_, rest) = fields.split_once("Connection to ")?;
+        let bssid = rest.split_whitespace().next()?;
+        let extra = rest
+            .split_once('[')
+            .map_or("", |(_, extra)| extra.trim_end().trim_end_matches(']'));
+        Some(Self::Connected {
+            bssid: bssid.parse().ok()?,
+            id: field(extra, "id").and_then(|id| id.parse().ok()),
+            // The id_str is last and may contain spaces
+            id_str: extra
+                .split_once("id_str=")
+                .map(|(_, id_str)| id_str)
+                .filter(|id_str| !id_str.is_empty())
+                .map(str::to_owned),
+        })
+    }
+
+    /// Parse `above=N signal=N noise=N txrate=N`
+    fn parse_signal_change(fields: &str) -> Option<Self> {
+        Some(Self::SignalChange {
+            above: field(fields, "above")? != "0",
+            signal: field(fields, "signal")?.parse().ok()?,
+            // 9999 means the driver does not report noise
+            noise: field(fields, "noise")
+                .filter(|noise| *noise != "9999")
+                .and_then(|noise| noise.parse().ok()),
+            txrate: field(fields, "txrate").and_then(|txrate| txrate.parse().ok()),
+        })
+    }
+
+    /// Parse `<addr> dialog_token=N status_code=N ... target_bssid=<bssid>`
+    fn parse_bss_tm_resp(fields: &str) -> Option<Self> {
+        Some(Self::BssTmResp {
+            addr: fields.split_whitespace().next()?.parse().ok()?,
+            dialog_token: field(fields, "dialog_token")?.parse().ok()?,
+            status_code: field(fields, "status_code")?.parse().ok()?,
+            target_bssid: field(fields, "target_bssid").and_then(|bssid| bssid.parse().ok()),
+        })
+    }
+
+    /// Parse the informational `WNM: ...` messages of BSS transition management
+    ///
+    /// Other `WNM:` lines, such as the details of a received request, are
+    /// debug traces that never reach the control interface.
+    fn parse_wnm(fields: &str) -> Option<Self> {
+        let timer = fields.strip_prefix("Disassociation Imminent - Disassociation Timer ")?;
+        Some(Self::DisassocImminent {
+            disassoc_timer: timer.trim().parse().ok()?,
+        })
+    }
+
+    /// Parse `id=N ssid="..." auth_failures=N duration=N reason=...`
+    fn parse_ssid_temp_disabled(fields: &str) -> Option<Self> {
+        let (ssid, rest) = quoted_field(fields, "ssid")?;
+        Some(Self::SsidTempDisabled {
+            id: field(fields, "id")?.parse().ok()?,
+            ssid: ssid.to_owned(),
+            // Only look after the SSID, which could contain anything
+            auth_failures: field(rest, "auth_failures")?.parse().ok()?,
+            duration: field(rest, "duration")?.parse().ok()?,
+            reason: field(rest, "reason").map(str::to_owned),
+        })
+    }
+
+    /// Parse `id=N ssid="..."`
+    fn parse_ssid_reenabled(fields: &str) -> Option<Self> {
+        let (ssid, _) = quoted_field(fields, "ssid")?;
+        Some(Self::SsidReenabled {
+            id: field(fields, "id")?.parse().ok()?,
+            ssid: ssid.to_owned(),
+        })
+    }
+
+    fn parse_assoc_reject(fields: &str) -> Option<Self> {
+        Some(Self::AssocReject {
+            bssid: field(fields, "bssid").and_then(|bssid| bssid.parse().ok()),
+            status_code: field(fields, "status_code")?.parse().ok()?,
+        })
+    }
+
+    fn parse_disconnected(fields: &str) -> Option<Self> {
+        Some(Self::Disconnected {
+            bssid: field(fields, "bssid")?.parse().ok()?,
+            reason: field(fields, "reason")?.parse::<u16>().ok()?.into(),
+            locally_generated: field(fields, "locally_generated") == Some("1"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn disconnect_reason() {
+        for code in 0..=u16::from(u8::MAX) {
+            assert_eq!(DisconnectReason::from(code).code(), code);
+        }
+        assert_eq!(DisconnectReason::from(4), DisconnectReason::Inactivity);
+        assert_eq!(DisconnectReason::from(99), DisconnectReason::Other(99));
+    }
+
+    #[test]
+    fn disconnected() {
+        assert_eq!(
+            Event::parse(
+                "<3>CTRL-EVENT-DISCONNECTED bssid=00:11:22:33:44:55 reason=15 locally_generated=1"
+            ),
+            Event::Disconnected {
+                bssid: Bssid::from([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]),
+                reason: DisconnectReason::FourwayHandshakeTimeout,
+                locally_generated: true,
+            }
+        );
+        assert_eq!(
+            Event::parse("CTRL-EVENT-DISCONNECTED bssid=00:11:22:33:44:55 reason=3"),
+            Event::Disconnected {
+                bssid: Bssid::from([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]),
+                reason: DisconnectReason::Deauth,
+                locally_generated: false,
+            }
+        );
+    }
+
+    #[test]
+    fn connected() {
+        assert_eq!(
+            Event::parse("<3>CTRL-EVENT-CONNECTED - Connection to 00:11:22:33:44:55 completed [id=0 id_str=]"),
+            Event::Connected {
+                bssid: Bssid::from([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]),
+                id: Some(0),
+                id_str: None,
+            }
+        );
+        assert_eq!(
+            Event::parse("<3>CTRL-EVENT-CONNECTED - Connection to 00:11:22:33:44:55 completed [id=3 id_str=home net]"),
+            Event::Connected {
+                bssid: Bssid::from([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]),
+                id: Some(3),
+                id_str: Some("home net".into()),
+            }
+        );
+        assert_eq!(
+            Event::parse("<3>CTRL-EVENT-CONNECTED - Connection to 00:11:22:33:44:55 completed"),
+            Event::Connected {
+                bssid: Bssid::from([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]),
+                id: None,
+                id_str: None,
+            }
+        );
+    }
+
+    #[test]
+    fn assoc_reject() {
+        assert_eq!(
+            Event::parse("<3>CTRL-EVENT-ASSOC-REJECT bssid=00:11:22:33:44:55 status_code=17"),
+            Event::AssocReject {
+                bssid: Some(Bssid::from([0x00, 0x11, 0x22, 0x33, 0x44, 0x55])),
+                status_code: 17,
+            }
+        );
+        assert_eq!(
+            Event::parse("<3>CTRL-EVENT-ASSOC-REJECT status_code=1"),
+            Event::AssocReject {
+                bssid: None,
+                status_code: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn bss_transition() {
+        assert_eq!(
+            Event::parse("<3>CTRL-EVENT-DISASSOC-IMMINENT disassoc_timer=200"),
+            Event::DisassocImminent { disassoc_timer: 200 }
+        );
+        assert_eq!(
+            Event::parse("<3>WNM: Disassociation Imminent - Disassociation Timer 15"),
+            Event::DisassocImminent { disassoc_timer: 15 }
+        );
+        assert!(matches!(
+            Event::parse("<3>WNM: BSS Transition Management Request: dialog_token=1"),
+            Event::Other(_)
+        ));
+        assert_eq!(
+            Event::parse(
+                "<3>BSS-TM-RESP 00:11:22:33:44:55 dialog_token=1 status_code=0 \
+                 bss_termination_delay=0 target_bssid=66:77:88:99:aa:bb"
+            ),
+            Event::BssTmResp {
+                addr: Bssid::from([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]),
+                dialog_token: 1,
+                status_code: 0,
+                target_bssid: Some(Bssid::from([0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb])),
+            }
+        );
+    }
+
+    #[test]
+    fn wps() {
+        assert_eq!(Event::parse("<3>WPS-SUCCESS "), Event::WpsSuccess);
+        assert_eq!(
+            Event::parse("<3>WPS-FAIL msg=8 config_error=15 reason=2 (M2 Timeout)"),
+            Event::WpsFail {
+                msg: Some(8),
+                config_error: Some(15),
+            }
+        );
+        assert_eq!(Event::parse("<3>WPS-TIMEOUT "), Event::WpsTimeout);
+        assert_eq!(Event::parse("<3>WPS-OVERLAP-DETECTED "), Event::WpsOverlapDetected);
+    }
+
+    #[test]
+    fn regdom_change() {
+        assert_eq!(
+            Event::parse("<3>CTRL-EVENT-REGDOM-CHANGE init=USER type=COUNTRY alpha2=US"),
+            Event::RegdomChange {
+                alpha2: "US".into(),
+                init: "USER".into(),
+            }
+        );
+        assert_eq!(
+            Event::parse("<3>CTRL-EVENT-REGDOM-CHANGE init=CORE type=WORLD"),
+            Event::RegdomChange {
+                alpha2: String::new(),
+                init: "CORE".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn signal_change() {
+        assert_eq!(
+            Event::parse("<3>CTRL-EVENT-SIGNAL-CHANGE above=0 signal=-78 noise=-95 txrate=6000"),
+            Event::SignalChange {
+                above: false,
+                signal: -78,
+                noise: Some(-95),
+                txrate: Some(6000),
+            }
+        );
+        assert_eq!(
+            Event::parse("<3>CTRL-EVENT-SIGNAL-CHANGE above=1 signal=-60 noise=9999 txrate=0"),
+            Event::SignalChange {
+                above: true,
+                signal: -60,
+                noise: None,
+                txrate: Some(0),
+            }
+        );
+    }
+
+    #[test]
+    fn sae() {
+        assert_eq!(
+            Event::parse("<3>CTRL-EVENT-SAE-UNKNOWN-PASSWORD-IDENTIFIER 00:11:22:33:44:55"),
+            Event::Sae {
+                kind: "UNKNOWN-PASSWORD-IDENTIFIER".into(),
+                addr: Some("00:11:22:33:44:55".parse().unwrap()),
+            }
+        );
+        assert_eq!(
+            Event::parse("<3>CTRL-EVENT-SAE-PK-FAILED"),
+            Event::Sae {
+                kind: "PK-FAILED".into(),
+                addr: None,
+            }
+        );
+    }
+
+    #[test]
+    fn ssid_temp_disabled() {
+        assert_eq!(
+            Event::parse(
+                "<3>CTRL-EVENT-SSID-TEMP-DISABLED id=1 ssid=\"my \\\"home\\\" duration=5\" auth_failures=2 duration=20 reason=WRONG_KEY"
+            ),
+            Event::SsidTempDisabled {
+                id: 1,
+                ssid: "my \\\"home\\\" duration=5".into(),
+                auth_failures: 2,
+                duration: 20,
+                reason: Some("WRONG_KEY".into()),
+            }
+        );
+        assert_eq!(
+            Event::parse("<3>CTRL-EVENT-SSID-REENABLED id=1 ssid=\"home\""),
+            Event::SsidReenabled {
+                id: 1,
+                ssid: "home".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn other() {
+        assert_eq!(
+            Event::parse("<3>CTRL-EVENT-SCAN-STARTED "),
+            Event::Other("CTRL-EVENT-SCAN-STARTED ".into())
+        );
+        assert_eq!(
+            Event::parse("<3>CTRL-EVENT-DISCONNECTED reason=bogus"),
+            Event::Other("CTRL-EVENT-DISCONNECTED reason=bogus".into())
+        );
+    }
+}
@@ -11,14 +11,40 @@
 //! println!("{}", wpa.request("LIST_NETWORKS").unwrap());
 //! ```
 //!
-//! The library currently only supports UNIX sockets, but additional
-//! connection methods (eg UDP or pipes) may be added in the future.
+//! The library supports UNIX sockets and, for remote control interfaces built
+//! with `CONFIG_CTRL_IFACE=udp`, UDP (see [`ClientBuilder::udp`]). Additional
+//! connection methods (eg pipes) may be added in the future.
+//!
+//! Enabling the `test-util` feature provides [`MockTransport`], which lets
+//! code built on this crate be tested without a running wpasupplicant.
 
+mod bssid;
+mod channel;
+mod clock;
+mod command;
 mod error;
+mod event;
+mod network;
+mod scan;
+mod status;
+mod transport;
 mod wpactrl;
-pub use crate::wpactrl::{Client, ClientAttached, ClientBuilder};
+pub use crate::bssid::Bssid;
+pub use crate::channel::{channel_to_freq, freq_to_channel, Band};
+pub use crate::clock::{Clock, SystemClock};
+#[cfg(any(test, feature = "test-util"))]
+pub use crate::clock::MockClock;
+pub use crate::command::{GlobalParam, InterfaceAddParams, LogLevel, PmksaEntry};
+pub use crate::event::{DisconnectReason, Event};
+pub use crate::network::{ConnectionInfo, EapParams, NetworkValue};
+pub use crate::scan::{Bss, BssMask, ScanResult, Security, SecurityFlags};
+pub use crate::status::{GlobalConfig, LinkInfo, Mode, Station, Status};
+pub use crate::transport::{Transport, UdpTransport};
+#[cfg(any(test, feature = "test-util"))]
+pub use crate::transport::MockTransport;
+pub use crate::wpactrl::{Client, ClientAttached, ClientBuilder, EventThread};
 
-pub use crate::error::Error;
+pub use crate::error::{Error, ParseError};
 
 /// A `Result` alias where the `Err` case is `wpactrl::Error`
 pub type Result<T> = ::std::result::Result<T, Error>;
@@ -0,0 +1,936 @@
+#![deny(missing_docs)]
+use super::Result;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::bssid::Bssid;
+use crate::error::Error;
+use crate::event::Event;
+use crate::status::Status;
+use crate::transport::Transport;
+use crate::wpactrl::{Client, ClientAttached};
+
+/// Consecutive authentication failures after which [`ClientAttached::ensure_connected`] gives up
+const MAX_AUTH_FAILURES: u32 = 2;
+
+/// First delay between attempts of [`ClientAttached::ensure_connected`], doubled after each one
+const RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Network parameters queried by [`Client::get_all_network`]
+const NETWORK_PARAMS: &[&str] = &[
+    "ssid",
+    "bssid",
+    "psk",
+    "key_mgmt",
+    "proto",
+    "pairwise",
+    "group",
+    "auth_alg",
+    "ieee80211w",
+    "priority",
+    "scan_ssid",
+    "disabled",
+    "id_str",
+    "mode",
+    "frequency",
+    "scan_freq",
+    "freq_list",
+    "bgscan",
+    "eap",
+    "identity",
+    "anonymous_identity",
+    "phase1",
+    "phase2",
+    "ca_cert",
+    "client_cert",
+    "wep_tx_keyidx",
+];
+
+/// Network parameters holding credentials, redacted before commands and replies are logged
+pub(crate) const SENSITIVE_KEYS: &[&str] = &[
+    "psk",
+    "password",
+    "sae_password",
+    "private_key_passwd",
+    "private_key2_passwd",
+    "wep_key0",
+    "wep_key1",
+    "wep_key2",
+    "wep_key3",
+    "pin",
+];
+
+/// What to report of an exchange, with credentials in `SET_NETWORK` / `GET_NETWORK` replaced by `***`
+pub(crate) fn redact<'a>(cmd: &'a str, reply: &'a str) -> (Cow<'a, str>, Cow<'a, str>) {
+    let body = match cmd.strip_prefix("IFNAME=") {
+        Some(rest) => rest.split_once(' ').map_or("", |(_, body)| body),
+        None => cmd,
+    };
+    let prefix = &cmd[..cmd.len() - body.len()];
+    let mut words = body.splitn(4, ' ');
+    let (verb, id, key) = (words.next(), words.next(), words.next());
+    let sensitive = key.is_some_and(|key| SENSITIVE_KEYS.contains(&key));
+    match (verb, id, key, words.next()) {
+        (Some("SET_NETWORK"), Some(id), Some(key), Some(_)) if sensitive => (
+            Cow::Owned(format!("{}SET_NETWORK {} {} ***", prefix, id, key)),
+            Cow::Borrowed(reply),
+        ),
+        (Some("GET_NETWORK"), _, Some(_), None) if sensitive && !reply.starts_with("FAIL") => {
+            (Cow::Borrowed(cmd), Cow::Borrowed("***"))
+        }
+        _ => (Cow::Borrowed(cmd), Cow::Borrowed(reply)),
+    }
+}
+
+/// A network parameter value, formatted the way `SET_NETWORK` expects it
+///
+/// # Examples
+///
+/// ```
+/// use wpactrl::NetworkValue;
+/// assert_eq!(NetworkValue::Str("home".into()).to_string(), "\"home\"");
+/// assert_eq!(NetworkValue::Str("a\tb".into()).to_string(), "610962");
+/// assert_eq!(NetworkValue::Raw("WPA-EAP".into()).to_string(), "WPA-EAP");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NetworkValue {
+    /// A string parameter such as `ssid` or `identity`
+    ///
+    /// It is quoted, or hex encoded if it contains control characters.
+    Str(String),
+    /// A parameter passed on verbatim, such as `key_mgmt` or `priority`
+    Raw(String),
+}
+
+impl NetworkValue {
+    /// The value of a `psk` parameter: a 64 digit hex key as is, a passphrase quoted
+    fn psk(psk: &str) -> Self {
+        if psk.len() == 64 && psk.bytes().all(|b| b.is_ascii_hexdigit()) {
+            Self::Raw(psk.to_owned())
+        } else {
+            // Passphrases cannot be hex encoded, and may not contain control characters anyway
+            Self::Raw(format!("\"{}\"", psk))
+        }
+    }
+}
+
+impl fmt::Display for NetworkValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Str(value) if value.chars().any(|c| c.is_ascii_control()) => {
+                value.bytes().try_for_each(|b| write!(f, "{:02x}", b))
+            }
+            Self::Str(value) => write!(f, "\"{}\"", value),
+            Self::Raw(value) => f.write_str(value),
+        }
+    }
+}
+
+/// Credentials of a WPA-EAP (enterprise) network, see [`Client::set_eap_network`]
+///
+/// # Examples
+///
+/// ```
+/// use wpactrl::EapParams;
+/// let params = EapParams {
+///     identity: Some("alice@example.com".into()),
+///     password: Some("secret".into()),
+///     phase2: Some("auth=MSCHAPV2".into()),
+///     ..EapParams::new("PEAP")
+/// };
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EapParams {
+    /// Accepted EAP methods, eg `PEAP` or `TTLS TLS`
+    pub eap: String,
+    /// Identity, eg a user name
+    pub identity: Option<String>,
+    /// Password for password based methods
+    pub password: Option<String>,
+    /// Path of the CA certificate used to verify the server
+    pub ca_cert: Option<String>,
+    /// Path of the client certificate for `TLS`
+    pub client_cert: Option<String>,
+    /// Path of the client private key for `TLS`
+    pub private_key: Option<String>,
+    /// Inner authentication, eg `auth=MSCHAPV2`
+    pub phase2: Option<String>,
+}
+
+impl EapParams {
+    /// Parameters for the `eap` methods, leaving every other field empty
+    #[must_use]
+    pub fn new<S: Into<String>>(eap: S) -> Self {
+        Self {
+            eap: eap.into(),
+            ..Self::default()
+        }
+    }
+
+    /// The `SET_NETWORK` parameters to send, in order
+    fn to_values(&self) -> Vec<(&'static str, NetworkValue)> {
+        let mut values = vec![
+            ("key_mgmt", NetworkValue::Raw("WPA-EAP".into())),
+            ("eap", NetworkValue::Raw(self.eap.clone())),
+        ];
+        let strings = [
+            ("identity", &self.identity),
+            ("password", &self.password),
+            ("ca_cert", &self.ca_cert),
+            ("client_cert", &self.client_cert),
+            ("private_key", &self.private_key),
+            ("phase2", &self.phase2),
+        ];
+        for (name, value) in strings {
+            if let Some(value) = value {
+                values.push((name, NetworkValue::Str(value.clone())));
+            }
+        }
+        values
+    }
+}
+
+/// The link established by [`ClientAttached::ensure_connected`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    /// Network name
+    pub ssid: String,
+    /// Access point the station is associated with
+    pub bssid: Bssid,
+    /// Id of the configured network in use
+    pub id: u32,
+    /// Frequency in MHz
+    pub freq: Option<u32>,
+    /// IP address of the interface, `None` until the DHCP client has configured it
+    pub ip_address: Option<String>,
+}
+
+impl ConnectionInfo {
+    /// The current link, if `status` shows a completed connection
+    fn from_status(status: Status) -> Option<Self> {
+        if status.wpa_state.as_deref() != Some("COMPLETED") {
+            return None;
+        }
+        Some(Self {
+            ssid: status.ssid?,
+            bssid: status.bssid?,
+            id: status.id?,
+            freq: status.freq,
+            ip_address: status.ip_address,
+        })
+    }
+}
+
+impl<T: Transport> Client<T> {
+    /// Create a new, disabled network, returning its id
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// let id = wpa.add_network().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (non-numeric) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn add_network(&mut self) -> Result<u32> {
+        let reply = self.request_raw("ADD_NETWORK")?;
+        parse_network_id(&reply)
+    }
+
+    /// Set a single parameter of a configured network
+    ///
+    /// `value` is passed on verbatim, so string parameters such as `ssid` must
+    /// be quoted (or hex encoded) by the caller.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.set_network(0, "priority", "5").unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unknown network, parameter or invalid value
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn set_network(&mut self, id: u32, name: &str, value: &str) -> Result<()> {
+        self.request_ok(&format!("SET_NETWORK {} {} {}", id, name, value))
+    }
+
+    /// Configure a network for WPA-EAP with the given credentials
+    ///
+    /// This sets `key_mgmt` to `WPA-EAP`, then each parameter given in `params`,
+    /// quoted as `wpa_supplicant` expects. The `ssid` is set separately.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use wpactrl::{EapParams, NetworkValue};
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// let id = wpa.add_network().unwrap();
+    /// wpa.set_network(id, "ssid", &NetworkValue::Str("corp".into()).to_string()).unwrap();
+    /// let params = EapParams {
+    ///     identity: Some("alice@example.com".into()),
+    ///     password: Some("secret".into()),
+    ///     ca_cert: Some("/etc/ssl/certs/corp-ca.pem".into()),
+    ///     phase2: Some("auth=MSCHAPV2".into()),
+    ///     ..EapParams::new("PEAP")
+    /// };
+    /// wpa.set_eap_network(id, &params).unwrap();
+    /// wpa.select_network(id).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unknown network or invalid value
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn set_eap_network(&mut self, id: u32, params: &EapParams) -> Result<()> {
+        for (name, value) in params.to_values() {
+            self.set_network(id, name, &value.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Configure a network for WPA3-SAE with the given password
+    ///
+    /// This sets `key_mgmt` to `SAE`, makes management frame protection
+    /// required as SAE mandates, and sets the quoted `sae_password`. The `ssid`
+    /// is set separately.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use wpactrl::NetworkValue;
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// let id = wpa.add_network().unwrap();
+    /// wpa.set_network(id, "ssid", &NetworkValue::Str("home".into()).to_string()).unwrap();
+    /// wpa.set_sae_network(id, "correct horse").unwrap();
+    /// wpa.enable_network(id).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unknown network, or SAE not supported by this build
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn set_sae_network(&mut self, id: u32, password: &str) -> Result<()> {
+        self.set_network(id, "key_mgmt", "SAE")?;
+        self.set_network(id, "ieee80211w", "2")?;
+        self.set_network(id, "sae_password", &NetworkValue::Str(password.to_owned()).to_string())
+    }
+
+    /// Enable a configured network
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.enable_network(0).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unknown network
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn enable_network(&mut self, id: u32) -> Result<()> {
+        self.request_ok(&format!("ENABLE_NETWORK {}", id))
+    }
+
+    /// Connect to a configured network, disabling all others
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.select_network(0).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unknown network
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn select_network(&mut self, id: u32) -> Result<()> {
+        self.request_ok(&format!("SELECT_NETWORK {}", id))
+    }
+
+    /// Remove a configured network
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.remove_network(0).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unknown network
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn remove_network(&mut self, id: u32) -> Result<()> {
+        self.request_ok(&format!("REMOVE_NETWORK {}", id))
+    }
+
+    /// Copy the parameter `param` of network `src_id` to network `dst_id`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// let id = wpa.add_network().unwrap();
+    /// wpa.dup_network(0, id, "psk").unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unknown network or parameter, or parameter not set on `src_id`
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn dup_network(&mut self, src_id: u32, dst_id: u32, param: &str) -> Result<()> {
+        self.request_ok(&format!("DUP_NETWORK {} {} {}", src_id, dst_id, param))
+    }
+
+    /// Add a network for `ssid` and connect to it, returning the new network id
+    ///
+    /// With a `psk` (an 8 to 63 character passphrase, or a 64 digit hex key)
+    /// the network uses WPA-PSK, otherwise it is open. The network is enabled
+    /// and selected, which disables every other network. If any step fails,
+    /// the partially configured network is removed again.
+    ///
+    /// This only starts the connection: wait for
+    /// `CTRL-EVENT-CONNECTED` on an attached client to know it succeeded.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// let id = wpa.connect("home", Some("correct horse battery staple")).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - A step was rejected, eg because of an invalid passphrase
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn connect(&mut self, ssid: &str, psk: Option<&str>) -> Result<u32> {
+        let id = self.add_network()?;
+        let r = self.configure_and_select(id, ssid, psk);
+        if r.is_err() {
+            // The original error is more useful than a failure to clean up
+            let _ = self.remove_network(id);
+        }
+        r.map(|()| id)
+    }
+
+    fn configure_and_select(&mut self, id: u32, ssid: &str, psk: Option<&str>) -> Result<()> {
+        self.set_network(id, "ssid", &NetworkValue::Str(ssid.into()).to_string())?;
+        match psk {
+            Some(psk) => self.set_network(id, "psk", &NetworkValue::psk(psk).to_string())?,
+            None => self.set_network(id, "key_mgmt", "NONE")?,
+        }
+        self.enable_network(id)?;
+        self.select_network(id)
+    }
+
+    /// Read a single parameter of a configured network
+    ///
+    /// Values are returned as `wpa_supplicant` stores them, so strings such as
+    /// `ssid` are quoted and secrets such as `psk` are usually masked as `*`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// println!("{}", wpa.get_network(0, "ssid").unwrap());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unknown network or unset parameter
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn get_network(&mut self, id: u32, name: &str) -> Result<String> {
+        let reply = self.request_raw(&format!("GET_NETWORK {} {}", id, name))?;
+        if reply == "FAIL\n" {
+            return Err(Error::CommandFailed("GET_NETWORK".into()));
+        }
+        Ok(reply)
+    }
+
+    /// Read the commonly used parameters of a configured network
+    ///
+    /// `wpa_supplicant` has no command to dump a whole network, so this queries
+    /// a fixed list of parameter names (`ssid`, `psk`, `key_mgmt`, `priority`,
+    /// `bssid`, `scan_ssid`, ...) one at a time, leaving out those it reports as
+    /// unset. Values are returned as by [`Client::get_network`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// for (name, value) in wpa.get_all_network(0).unwrap() {
+    ///     println!("{}={}", name, value);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unknown network
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn get_all_network(&mut self, id: u32) -> Result<HashMap<String, String>> {
+        let mut params = HashMap::new();
+        for name in NETWORK_PARAMS {
+            match self.get_network(id, name) {
+                Ok(value) => {
+                    params.insert((*name).to_owned(), value);
+                }
+                Err(Error::CommandFailed(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if params.is_empty() {
+            return Err(Error::CommandFailed("GET_NETWORK".into()));
+        }
+        Ok(params)
+    }
+}
+
+/// Parse the reply of `ADD_NETWORK`
+fn parse_network_id(reply: &str) -> Result<u32> {
+    reply
+        .trim_end()
+        .parse()
+        .map_err(|_| Error::CommandFailed("ADD_NETWORK".into()))
+}
+
+impl<T: Transport> ClientAttached<T> {
+    /// Like [`Client::add_network`], buffering messages received meanwhile
+    pub(crate) fn add_network(&mut self) -> Result<u32> {
+        let reply = self.request_raw("ADD_NETWORK")?;
+        parse_network_id(&reply)
+    }
+
+    /// Like [`Client::set_network`], buffering messages received meanwhile
+    pub(crate) fn set_network(&mut self, id: u32, name: &str, value: &str) -> Result<()> {
+        self.request_ok(&format!("SET_NETWORK {} {} {}", id, name, value))
+    }
+
+    /// Like [`Client::select_network`], buffering messages received meanwhile
+    pub(crate) fn select_network(&mut self, id: u32) -> Result<()> {
+        self.request_ok(&format!("SELECT_NETWORK {}", id))
+    }
+
+    /// Like [`Client::remove_network`], buffering messages received meanwhile
+    pub(crate) fn remove_network(&mut self, id: u32) -> Result<()> {
+        self.request_ok(&format!("REMOVE_NETWORK {}", id))
+    }
+
+    /// Make sure the station is connected to `ssid`, connecting if needed
+    ///
+    /// If already connected to `ssid`, this returns at once. Otherwise it
+    /// scans, adds a network for `ssid` as [`Client::connect`] does, and waits
+    /// for the connection. Failed attempts, eg association rejections, are
+    /// retried with an increasing delay until `timeout`. The added network is
+    /// removed again if no connection could be made. Control interface
+    /// messages received meanwhile stay buffered.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// let info = wpa.ensure_connected("home", Some("correct horse"), Duration::from_secs(60)).unwrap();
+    /// println!("connected to {} on network {}", info.bssid, info.id);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - A step was rejected, eg because of an invalid passphrase
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::NetworkNotFound`] - No access point for `ssid` was found before `timeout`
+    /// * [`Error::Parse`] - Malformed reply
+    /// * [`Error::ScanBusy`] - Another scan started before this one could
+    /// * [`Error::Timeout`] - Not connected within `timeout`
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    /// * [`Error::WrongKey`] - Authentication failed repeatedly
+    pub fn ensure_connected(&mut self, ssid: &str, psk: Option<&str>, timeout: Duration) -> Result<ConnectionInfo> {
+        let deadline = self.now() + timeout;
+        if let Some(info) = self.connection_info(ssid)? {
+            return Ok(info);
+        }
+        let mut id = None;
+        let r = self.connect_until(ssid, psk, deadline, &mut id);
+        if let (Err(_), Some(id)) = (&r, id) {
+            // The original error is more useful than a failure to clean up
+            let _ = self.remove_network(id);
+        }
+        r
+    }
+
+    /// The current link, if connected to `ssid`
+    fn connection_info(&mut self, ssid: &str) -> Result<Option<ConnectionInfo>> {
+        let info = ConnectionInfo::from_status(self.status()?);
+        Ok(info.filter(|info| info.ssid == ssid))
+    }
+
+    /// Scan, connect and retry until `deadline`, storing the id of the network added in `id`
+    fn connect_until(
+        &mut self,
+        ssid: &str,
+        psk: Option<&str>,
+        deadline: Instant,
+        id: &mut Option<u32>,
+    ) -> Result<ConnectionInfo> {
+        let mut backoff = RETRY_BACKOFF;
+        loop {
+            let results = self.scan_and_wait(deadline.saturating_duration_since(self.now()))?;
+            let found = results.iter().any(|bss| bss.ssid == ssid);
+            if found {
+                let net = match *id {
+                    Some(net) => net,
+                    None => *id.insert(self.add_network_for(ssid, psk)?),
+                };
+                let start = self.buffered();
+                self.select_network(net)?;
+                if self.wait_connected_to(start, net, deadline)? {
+                    if let Some(info) = self.connection_info(ssid)? {
+                        return Ok(info);
+                    }
+                }
+            }
+            if self.now() + backoff >= deadline {
+                return Err(if found {
+                    Error::Timeout
+                } else {
+                    Error::NetworkNotFound(ssid.to_owned())
+                });
+            }
+            // Waiting for nothing keeps messages buffered, and uses the client's clock
+            if let Err(e) = self.wait_for_from(self.buffered(), |_| false, backoff) {
+                if !matches!(e, Error::Timeout) {
+                    return Err(e);
+                }
+            }
+            backoff *= 2;
+        }
+    }
+
+    /// Add a network for `ssid`, with WPA-PSK if given a `psk`, returning its id
+    fn add_network_for(&mut self, ssid: &str, psk: Option<&str>) -> Result<u32> {
+        let id = self.add_network()?;
+        let key = match psk {
+            Some(psk) => ("psk", NetworkValue::psk(psk)),
+            None => ("key_mgmt", NetworkValue::Raw("NONE".into())),
+        };
+        for (name, value) in [("ssid", NetworkValue::Str(ssid.into())), key] {
+            // The caller only learns the id on success, so clean up here
+            if let Err(e) = self.set_network(id, name, &value.to_string()) {
+                let _ = self.remove_network(id);
+                return Err(e);
+            }
+        }
+        Ok(id)
+    }
+
+    /// Wait for the outcome of connecting to network `net`, ignoring the first `start` buffered messages
+    ///
+    /// Returns whether the station connected, or `false` if the attempt was rejected.
+    fn wait_connected_to(&mut self, start: usize, net: u32, deadline: Instant) -> Result<bool> {
+        loop {
+            let msg = self.wait_for_from(
+                start,
+                |msg| match Event::parse(msg) {
+                    Event::Connected { .. } | Event::AssocReject { .. } => true,
+                    Event::SsidTempDisabled { id, .. } => id == net,
+                    _ => false,
+                },
+                deadline.saturating_duration_since(self.now()),
+            )?;
+            match Event::parse(&msg) {
+                Event::Connected { .. } => return Ok(true),
+                Event::AssocReject { .. } => return Ok(false),
+                Event::SsidTempDisabled {
+                    auth_failures,
+                    reason,
+                    ..
+                } if reason.as_deref() == Some("WRONG_KEY") && auth_failures >= MAX_AUTH_FAILURES => {
+                    return Err(Error::WrongKey)
+                }
+                // wpa_supplicant tries again by itself once the network is re-enabled
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::transport::{mock_ctrl, MockTransport};
+
+    #[test]
+    fn redact() {
+        assert_eq!(
+            super::redact("SET_NETWORK 0 psk \"secret pass\"", "OK\n"),
+            ("SET_NETWORK 0 psk ***".into(), "OK\n".into())
+        );
+        assert_eq!(
+            super::redact("IFNAME=wlan0 SET_NETWORK 1 password \"secret\"", "OK\n").0,
+            "IFNAME=wlan0 SET_NETWORK 1 password ***"
+        );
+        assert_eq!(super::redact("GET_NETWORK 0 psk", "\"secret\"").1, "***");
+        assert_eq!(super::redact("GET_NETWORK 0 psk", "FAIL\n").1, "FAIL\n");
+        assert_eq!(
+            super::redact("SET_NETWORK 0 ssid \"home\"", "OK\n"),
+            ("SET_NETWORK 0 ssid \"home\"".into(), "OK\n".into())
+        );
+    }
+
+    #[test]
+    fn connect() {
+        let (mock, mut wpa) = mock_ctrl();
+        for reply in ["1\n", "OK\n", "OK\n", "OK\n", "OK\n"] {
+            mock.push_reply(reply);
+        }
+        assert_eq!(wpa.connect("home \"5G\"", Some("passphrase")).unwrap(), 1);
+        assert_eq!(
+            mock.sent(),
+            vec![
+                "ADD_NETWORK",
+                "SET_NETWORK 1 ssid \"home \"5G\"\"",
+                "SET_NETWORK 1 psk \"passphrase\"",
+                "ENABLE_NETWORK 1",
+                "SELECT_NETWORK 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn connect_open_fails() {
+        let (mock, mut wpa) = mock_ctrl();
+        for reply in ["2\n", "OK\n", "FAIL\n", "OK\n"] {
+            mock.push_reply(reply);
+        }
+        assert!(matches!(wpa.connect("cafe", None), Err(Error::CommandFailed(ref cmd)) if cmd == "SET_NETWORK"));
+        assert_eq!(
+            mock.sent(),
+            vec![
+                "ADD_NETWORK",
+                "SET_NETWORK 2 ssid \"cafe\"",
+                "SET_NETWORK 2 key_mgmt NONE",
+                "REMOVE_NETWORK 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn set_eap_network() {
+        let (mock, mut wpa) = mock_ctrl();
+        for _ in 0..4 {
+            mock.push_reply("OK\n");
+        }
+        let params = EapParams {
+            identity: Some("alice".into()),
+            password: Some("pass\nword".into()),
+            ..EapParams::new("PEAP")
+        };
+        wpa.set_eap_network(3, &params).unwrap();
+        assert_eq!(
+            mock.sent(),
+            vec![
+                "SET_NETWORK 3 key_mgmt WPA-EAP",
+                "SET_NETWORK 3 eap PEAP",
+                "SET_NETWORK 3 identity \"alice\"",
+                "SET_NETWORK 3 password 706173730a776f7264",
+            ]
+        );
+    }
+
+    #[test]
+    fn set_sae_network() {
+        let (mock, mut wpa) = mock_ctrl();
+        for _ in 0..3 {
+            mock.push_reply("OK\n");
+        }
+        wpa.set_sae_network(2, "correct horse").unwrap();
+        assert_eq!(
+            mock.sent(),
+            vec![
+                "SET_NETWORK 2 key_mgmt SAE",
+                "SET_NETWORK 2 ieee80211w 2",
+                "SET_NETWORK 2 sae_password \"correct horse\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn dup_network() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        mock.push_reply("FAIL\n");
+        wpa.dup_network(0, 1, "psk").unwrap();
+        assert!(matches!(
+            wpa.dup_network(0, 7, "psk"),
+            Err(Error::CommandFailed(ref cmd)) if cmd == "DUP_NETWORK"
+        ));
+        assert_eq!(mock.sent(), vec!["DUP_NETWORK 0 1 psk", "DUP_NETWORK 0 7 psk"]);
+    }
+
+    const SCAN_RESULTS: &str = "bssid / frequency / signal level / flags / ssid\n\
+                                00:11:22:33:44:55\t2412\t-40\t[WPA2-PSK-CCMP][ESS]\thome\n";
+    const COMPLETED: &str = "bssid=00:11:22:33:44:55\nfreq=2412\nssid=home\nid=0\n\
+                             wpa_state=COMPLETED\nip_address=192.168.1.20\n";
+
+    fn mock_attached() -> (MockTransport, ClientAttached<MockTransport>) {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let clock = MockClock::ticking(Duration::from_millis(1));
+        let wpa = wpa.with_clock(clock).attach().unwrap();
+        (mock, wpa)
+    }
+
+    /// Queue the replies to `SCAN`, up to the results
+    fn push_scan(mock: &MockTransport, results: &str) {
+        mock.push_reply("OK\n");
+        mock.push_reply("<3>CTRL-EVENT-SCAN-RESULTS ");
+        mock.push_reply(results);
+    }
+
+    #[test]
+    fn ensure_connected() {
+        let (mock, mut wpa) = mock_attached();
+        mock.push_reply("wpa_state=DISCONNECTED\n");
+        push_scan(&mock, SCAN_RESULTS);
+        mock.push_reply("0\n");
+        for _ in 0..3 {
+            mock.push_reply("OK\n");
+        }
+        mock.push_reply("<3>CTRL-EVENT-CONNECTED - Connection to 00:11:22:33:44:55 completed [id=0 id_str=]");
+        mock.push_reply(COMPLETED);
+        let info = wpa.ensure_connected("home", Some("correct horse"), Duration::from_secs(60)).unwrap();
+        assert_eq!(info.id, 0);
+        assert_eq!(info.ip_address.as_deref(), Some("192.168.1.20"));
+        assert_eq!(
+            mock.sent()[1..],
+            [
+                "STATUS",
+                "SCAN",
+                "SCAN_RESULTS",
+                "ADD_NETWORK",
+                "SET_NETWORK 0 ssid \"home\"",
+                "SET_NETWORK 0 psk \"correct horse\"",
+                "SELECT_NETWORK 0",
+                "STATUS",
+            ]
+        );
+
+        mock.push_reply(COMPLETED);
+        wpa.ensure_connected("home", None, Duration::from_secs(60)).unwrap();
+        assert_eq!(mock.sent().len(), 10);
+    }
+
+    #[test]
+    fn ensure_connected_wrong_key() {
+        let (mock, mut wpa) = mock_attached();
+        mock.push_reply("wpa_state=SCANNING\n");
+        push_scan(&mock, SCAN_RESULTS);
+        mock.push_reply("0\n");
+        for _ in 0..3 {
+            mock.push_reply("OK\n");
+        }
+        for failures in 1..=2 {
+            mock.push_reply(format!(
+                "<3>CTRL-EVENT-SSID-TEMP-DISABLED id=0 ssid=\"home\" auth_failures={} duration=10 reason=WRONG_KEY",
+                failures
+            ));
+        }
+        mock.push_reply("OK\n");
+        assert!(matches!(
+            wpa.ensure_connected("home", Some("wrong password"), Duration::from_secs(60)),
+            Err(Error::WrongKey)
+        ));
+        assert_eq!(mock.sent().last().unwrap(), "REMOVE_NETWORK 0");
+    }
+
+    #[test]
+    fn ensure_connected_not_found() {
+        let (mock, mut wpa) = mock_attached();
+        mock.push_reply("wpa_state=DISCONNECTED\n");
+        push_scan(&mock, "bssid / frequency / signal level / flags / ssid\n");
+        assert!(matches!(
+            wpa.ensure_connected("home", None, Duration::from_millis(500)),
+            Err(Error::NetworkNotFound(ref ssid)) if ssid == "home"
+        ));
+        assert_eq!(mock.sent()[1..], ["STATUS", "SCAN", "SCAN_RESULTS"]);
+    }
+
+    #[test]
+    fn get_all_network() {
+        let (mock, mut wpa) = mock_ctrl();
+        for name in NETWORK_PARAMS {
+            match *name {
+                "ssid" => mock.push_reply("\"home\""),
+                "key_mgmt" => mock.push_reply("WPA-PSK"),
+                _ => mock.push_reply("FAIL\n"),
+            }
+        }
+        let params = wpa.get_all_network(0).unwrap();
+        assert_eq!(params.len(), 2);
+        assert_eq!(params["ssid"], "\"home\"");
+        assert_eq!(params["key_mgmt"], "WPA-PSK");
+        assert_eq!(mock.sent()[0], "GET_NETWORK 0 ssid");
+        assert_eq!(mock.sent().len(), NETWORK_PARAMS.len());
+
+        for _ in NETWORK_PARAMS {
+            mock.push_reply("FAIL\n");
+        }
+        assert!(matches!(wpa.get_all_network(7), Err(Error::CommandFailed(_))));
+    }
+}
@@ -0,0 +1,816 @@
+#![deny(missing_docs)]
+use super::Result;
+use std::collections::HashMap;
+use std::ops::{BitOr, BitOrAssign};
+use std::time::{Duration, Instant};
+
+use crate::bssid::Bssid;
+use crate::channel::freq_to_channel;
+use crate::error::{Error, ParseError, ParseResult};
+use crate::event;
+use crate::transport::Transport;
+use crate::wpactrl::{Client, ClientAttached};
+
+/// Selects which fields `wpa_supplicant` reports for each BSS
+///
+/// Values mirror the `WPA_BSS_MASK_*` bits of `wpa_supplicant`. Masks can be
+/// combined with `|`.
+///
+/// # Examples
+///
+/// ```
+/// use wpactrl::BssMask;
+/// let mask = BssMask::BSSID | BssMask::SSID | BssMask::LEVEL;
+/// assert!(mask.contains(BssMask::SSID));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BssMask(u32);
+
+impl BssMask {
+    /// `id`
+    pub const ID: Self = Self(1 << 0);
+    /// `bssid`
+    pub const BSSID: Self = Self(1 << 1);
+    /// `freq`
+    pub const FREQ: Self = Self(1 << 2);
+    /// `beacon_int`
+    pub const BEACON_INT: Self = Self(1 << 3);
+    /// `capabilities`
+    pub const CAPABILITIES: Self = Self(1 << 4);
+    /// `qual`
+    pub const QUAL: Self = Self(1 << 5);
+    /// `noise`
+    pub const NOISE: Self = Self(1 << 6);
+    /// `level`
+    pub const LEVEL: Self = Self(1 << 7);
+    /// `tsf`
+    pub const TSF: Self = Self(1 << 8);
+    /// `age`
+    pub const AGE: Self = Self(1 << 9);
+    /// `ie`
+    pub const IE: Self = Self(1 << 10);
+    /// `flags`
+    pub const FLAGS: Self = Self(1 << 11);
+    /// `ssid`
+    pub const SSID: Self = Self(1 << 12);
+    /// `====` line between entries, always requested by [`Client::bss_range`]
+    pub const DELIM: Self = Self(1 << 17);
+
+    /// Every field reported by `wpa_supplicant`
+    pub const ALL: Self = Self(0xFFFD_FFFF);
+
+    /// The raw mask value
+    #[must_use]
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Whether every bit of `other` is also set in `self`
+    #[must_use]
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for BssMask {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for BssMask {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// A BSS known to `wpa_supplicant`, as reported by the `BSS` command
+///
+/// Fields are `None` when they were excluded by the [`BssMask`] or missing from the reply.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bss {
+    /// Identifier of the BSS within `wpa_supplicant`
+    pub id: Option<u32>,
+    /// Hardware address of the access point
+    pub bssid: Option<Bssid>,
+    /// Frequency in MHz
+    pub freq: Option<u32>,
+    /// Beacon interval in TUs
+    pub beacon_int: Option<u32>,
+    /// Capability information field
+    pub capabilities: Option<String>,
+    /// Signal quality
+    pub qual: Option<i32>,
+    /// Noise level
+    pub noise: Option<i32>,
+    /// Signal level in dBm
+    pub level: Option<i32>,
+    /// Timing synchronization function timer
+    pub tsf: Option<u64>,
+    /// Seconds since the BSS was last seen
+    pub age: Option<u32>,
+    /// Information elements, hex encoded
+    pub ie: Option<String>,
+    /// Flags such as `[WPA2-PSK-CCMP][ESS]`
+    pub flags: Option<String>,
+    /// Network name
+    pub ssid: Option<String>,
+    /// Remaining `key=value` fields without a dedicated member
+    pub extra: HashMap<String, String>,
+}
+
+fn parse_field<F: std::str::FromStr>(key: &str, value: &str) -> ParseResult<F> {
+    value
+        .parse()
+        .map_err(|_| ParseError::new(format!("invalid BSS {} {:?}", key, value)))
+}
+
+impl Bss {
+    /// Parse a single BSS from its `key=value` lines
+    fn parse(entry: &str) -> ParseResult<Self> {
+        let mut bss = Self::default();
+        for line in entry.lines().filter(|line| !line.is_empty()) {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| ParseError::new(format!("invalid BSS line {:?}", line)))?;
+            match key {
+                "id" => bss.id = Some(parse_field(key, value)?),
+                "bssid" => bss.bssid = Some(value.parse()?),
+                "freq" => bss.freq = Some(parse_field(key, value)?),
+                "beacon_int" => bss.beacon_int = Some(parse_field(key, value)?),
+                "capabilities" => bss.capabilities = Some(value.to_owned()),
+                "qual" => bss.qual = Some(parse_field(key, value)?),
+                "noise" => bss.noise = Some(parse_field(key, value)?),
+                "level" => bss.level = Some(parse_field(key, value)?),
+                "tsf" => bss.tsf = Some(parse_field(key, value)?),
+                "age" => bss.age = Some(parse_field(key, value)?),
+                "ie" => bss.ie = Some(value.to_owned()),
+                "flags" => bss.flags = Some(value.to_owned()),
+                "ssid" => bss.ssid = Some(value.to_owned()),
+                _ => {
+                    bss.extra.insert(key.to_owned(), value.to_owned());
+                }
+            }
+        }
+        Ok(bss)
+    }
+
+    /// Parse the `====`-delimited reply of a `BSS RANGE=` command
+    ///
+    /// # Errors
+    ///
+    /// * [`ParseError`] - Malformed BSS entry
+    pub fn parse_range(reply: &str) -> ParseResult<Vec<Self>> {
+        reply
+            .split("====\n")
+            .filter(|entry| !entry.trim().is_empty())
+            .map(Self::parse)
+            .collect()
+    }
+}
+
+/// A row of the `SCAN_RESULTS` table
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ScanResult {
+    /// Hardware address of the access point
+    pub bssid: Bssid,
+    /// Frequency in MHz
+    pub frequency: u32,
+    /// Signal level, usually in dBm (more negative is weaker)
+    pub signal_level: i32,
+    /// Flags such as `[WPA2-PSK-CCMP][ESS]`
+    pub flags: String,
+    /// Network name
+    pub ssid: String,
+}
+
+impl ScanResult {
+    /// Parse a single tab separated row
+    fn parse(line: &str) -> ParseResult<Self> {
+        let invalid = || ParseError::new(format!("invalid scan result {:?}", line));
+        let mut fields = line.splitn(5, '\t');
+        let mut next = || fields.next().ok_or_else(invalid);
+        Ok(Self {
+            bssid: next()?.parse()?,
+            frequency: next()?.parse().map_err(|_| invalid())?,
+            signal_level: next()?.parse().map_err(|_| invalid())?,
+            flags: next()?.to_owned(),
+            ssid: next()?.to_owned(),
+        })
+    }
+
+    /// The channel number of [`ScanResult::frequency`], see [`freq_to_channel`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::ScanResult;
+    /// let reply = "bssid / frequency / signal level / flags / ssid\n\
+    ///              00:11:22:33:44:55\t5180\t-40\t[ESS]\thome\n";
+    /// let results = ScanResult::parse_table(reply).unwrap();
+    /// assert_eq!(results[0].channel(), Some(36));
+    /// ```
+    #[must_use]
+    pub fn channel(&self) -> Option<u32> {
+        freq_to_channel(self.frequency)
+    }
+
+    /// The security information in [`ScanResult::flags`]
+    #[must_use]
+    pub fn security_flags(&self) -> SecurityFlags {
+        SecurityFlags::parse(&self.flags)
+    }
+
+    /// How the access point is secured, see [`SecurityFlags::security`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::{ScanResult, Security};
+    /// let reply = "bssid / frequency / signal level / flags / ssid\n\
+    ///              00:11:22:33:44:55\t2412\t-40\t[WPA2-PSK-CCMP][ESS]\thome\n";
+    /// let results = ScanResult::parse_table(reply).unwrap();
+    /// assert_eq!(results[0].security(), Security::Wpa2Psk);
+    /// ```
+    #[must_use]
+    pub fn security(&self) -> Security {
+        self.security_flags().security()
+    }
+
+    /// Parse the reply of a `SCAN_RESULTS` command, skipping its header row
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::ScanResult;
+    /// let reply = "bssid / frequency / signal level / flags / ssid\n\
+    ///              00:11:22:33:44:55\t2412\t-40\t[ESS]\thome\n";
+    /// let results = ScanResult::parse_table(reply).unwrap();
+    /// assert_eq!(results[0].ssid, "home");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`ParseError`] - Malformed scan result
+    pub fn parse_table(reply: &str) -> ParseResult<Vec<Self>> {
+        reply
+            .lines()
+            .skip(1)
+            .filter(|line| !line.is_empty())
+            .map(Self::parse)
+            .collect()
+    }
+}
+
+/// Security information from the flags of a scan result, eg `[WPA2-PSK-CCMP][ESS][WPS]`
+///
+/// Flags can be combined with `|`.
+///
+/// # Examples
+///
+/// ```
+/// use wpactrl::SecurityFlags;
+/// let flags = SecurityFlags::parse("[WPA2-PSK+SAE-CCMP][ESS]");
+/// assert!(flags.contains(SecurityFlags::WPA2 | SecurityFlags::PSK | SecurityFlags::SAE));
+/// assert!(!flags.contains(SecurityFlags::WPS));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SecurityFlags(u32);
+
+impl SecurityFlags {
+    /// WPA (version 1)
+    pub const WPA: Self = Self(1 << 0);
+    /// WPA2, reported as `WPA2` or `RSN`
+    pub const WPA2: Self = Self(1 << 1);
+    /// WPA3, implied by SAE or OWE over RSN
+    pub const WPA3: Self = Self(1 << 2);
+    /// Pre-shared key authentication
+    pub const PSK: Self = Self(1 << 3);
+    /// Simultaneous authentication of equals, the WPA3 personal mode
+    pub const SAE: Self = Self(1 << 4);
+    /// 802.1X authentication, the enterprise modes
+    pub const EAP: Self = Self(1 << 5);
+    /// Opportunistic wireless encryption
+    pub const OWE: Self = Self(1 << 6);
+    /// WEP encryption
+    pub const WEP: Self = Self(1 << 7);
+    /// Wi-Fi Protected Setup is available
+    pub const WPS: Self = Self(1 << 8);
+    /// Infrastructure network, as opposed to ad hoc
+    pub const ESS: Self = Self(1 << 9);
+
+    /// Parse a flags field, ignoring unknown flags
+    #[must_use]
+    pub fn parse(flags: &str) -> Self {
+        let mut parsed = Self::default();
+        for flag in flags.split(['[', ']']).filter(|flag| !flag.is_empty()) {
+            parsed |= match flag {
+                "WEP" => Self::WEP,
+                "ESS" => Self::ESS,
+                _ if flag.starts_with("WPS") => Self::WPS,
+                _ => Self::parse_akm(flag),
+            };
+        }
+        parsed
+    }
+
+    /// Parse a `<proto>-<key management>-<cipher>` flag, eg `WPA2-PSK+SAE-CCMP` or `RSN-FT/EAP-CCMP`
+    fn parse_akm(flag: &str) -> Self {
+        let (proto, rest) = flag.split_once('-').unwrap_or((flag, ""));
+        let mut parsed = match proto {
+            "WPA" => Self::WPA,
+            "WPA2" | "RSN" => Self::WPA2,
+            "WPA3" => Self::WPA3,
+            _ => return Self::default(),
+        };
+        for part in rest.split(['-', '+', '/']) {
+            parsed |= match part {
+                "PSK" => Self::PSK,
+                "SAE" => Self::SAE,
+                "EAP" => Self::EAP,
+                "OWE" => Self::OWE,
+                _ => Self::default(),
+            };
+        }
+        if parsed.contains(Self::WPA2) && (parsed.contains(Self::SAE) || parsed.contains(Self::OWE)) {
+            parsed |= Self::WPA3;
+        }
+        parsed
+    }
+
+    /// The raw flag bits
+    #[must_use]
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Whether every flag of `other` is also set in `self`
+    #[must_use]
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The strongest kind of security the flags allow
+    #[must_use]
+    pub fn security(self) -> Security {
+        if self.contains(Self::EAP) {
+            Security::Enterprise
+        } else if self.contains(Self::SAE) {
+            if self.contains(Self::PSK) {
+                Security::Wpa2Wpa3
+            } else {
+                Security::Wpa3Sae
+            }
+        } else if self.contains(Self::OWE) {
+            Security::Owe
+        } else if self.contains(Self::PSK) {
+            if self.contains(Self::WPA2) {
+                Security::Wpa2Psk
+            } else {
+                Security::WpaPsk
+            }
+        } else if self.contains(Self::WEP) {
+            Security::Wep
+        } else {
+            Security::Open
+        }
+    }
+}
+
+impl BitOr for SecurityFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for SecurityFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// How an access point is secured, as derived from its [`SecurityFlags`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Security {
+    /// No encryption
+    Open,
+    /// Opportunistic wireless encryption, without authentication
+    Owe,
+    /// WEP, which is broken
+    Wep,
+    /// WPA (version 1) with a pre-shared key
+    WpaPsk,
+    /// WPA2 with a pre-shared key
+    Wpa2Psk,
+    /// WPA2/WPA3 transition mode, accepting both a pre-shared key and SAE
+    Wpa2Wpa3,
+    /// WPA3 personal, with SAE
+    Wpa3Sae,
+    /// 802.1X authentication, with any WPA version
+    Enterprise,
+}
+
+/// Fail with [`Error::ScanBusy`] or [`Error::CommandFailed`] unless `reply` is `OK`
+fn check_scan(reply: &str) -> Result<()> {
+    match reply {
+        "OK\n" => Ok(()),
+        _ if reply.starts_with("FAIL-BUSY") => Err(Error::ScanBusy),
+        _ => Err(Error::CommandFailed("SCAN".into())),
+    }
+}
+
+impl<T: Transport> Client<T> {
+    /// Start scanning for access points
+    ///
+    /// Results are available once `CTRL-EVENT-SCAN-RESULTS` is received, see
+    /// [`ClientAttached::scan_and_wait`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// match wpa.scan() {
+    ///     Ok(()) | Err(wpactrl::Error::ScanBusy) => {}
+    ///     Err(e) => panic!("{}", e),
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (non-OK) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::ScanBusy`] - A scan is already running
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn scan(&mut self) -> Result<()> {
+        let reply = self.request_raw("SCAN")?;
+        check_scan(&reply)
+    }
+
+    /// Fetch the results of the latest scan
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// for bss in wpa.scan_results().unwrap() {
+    ///     println!("{} {} {}", bss.bssid, bss.signal_level, bss.ssid);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Parse`] - Malformed scan result
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn scan_results(&mut self) -> Result<Vec<ScanResult>> {
+        let reply = self.request_raw("SCAN_RESULTS")?;
+        Ok(ScanResult::parse_table(&reply)?)
+    }
+
+    /// Fetch the results of the latest scan, strongest signal first
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// if let Some(bss) = wpa.scan_results_sorted().unwrap().first() {
+    ///     println!("strongest: {} ({} dBm)", bss.ssid, bss.signal_level);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Parse`] - Malformed scan result
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn scan_results_sorted(&mut self) -> Result<Vec<ScanResult>> {
+        let mut results = self.scan_results()?;
+        results.sort_by_key(|bss| std::cmp::Reverse(bss.signal_level));
+        Ok(results)
+    }
+
+    /// Find the access point with the strongest signal advertising `ssid`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// if let Some(bss) = wpa.best_bss_for_ssid("home").unwrap() {
+    ///     println!("best access point: {}", bss.bssid);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Parse`] - Malformed scan result
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn best_bss_for_ssid(&mut self, ssid: &str) -> Result<Option<ScanResult>> {
+        Ok(self
+            .scan_results()?
+            .into_iter()
+            .filter(|bss| bss.ssid == ssid)
+            .max_by_key(|bss| bss.signal_level))
+    }
+
+    /// Fetch every BSS whose identifier lies within `start..=end` in a single request
+    ///
+    /// Only the fields selected by `mask` are reported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::BssMask;
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// for bss in wpa.bss_range(0, 100, BssMask::BSSID | BssMask::SSID).unwrap() {
+    ///     println!("{:?} {:?}", bss.bssid, bss.ssid);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Parse`] - Malformed BSS entry
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn bss_range(&mut self, start: u32, end: u32, mask: BssMask) -> Result<Vec<Bss>> {
+        self.bss_request(&format!("{}-{}", start, end), mask)
+    }
+
+    /// Fetch the results of the latest scans, leaving out BSSes last seen more than `max_age` ago
+    ///
+    /// Unlike `SCAN_RESULTS`, the `BSS` command reports how long ago each
+    /// BSS was seen, so this gives a view of what is actually around right now.
+    /// BSSes whose age is not reported are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// for bss in wpa.scan_results_fresh(Duration::from_secs(30)).unwrap() {
+    ///     println!("{} {} {}", bss.bssid, bss.signal_level, bss.ssid);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (FAIL) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Parse`] - Malformed BSS entry
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn scan_results_fresh(&mut self, max_age: Duration) -> Result<Vec<ScanResult>> {
+        let mask = BssMask::BSSID | BssMask::FREQ | BssMask::LEVEL | BssMask::AGE | BssMask::FLAGS | BssMask::SSID;
+        Ok(self
+            .bss_request("ALL", mask)?
+            .into_iter()
+            .filter(|bss| bss.age.is_some_and(|age| Duration::from_secs(age.into()) <= max_age))
+            .filter_map(|bss| {
+                Some(ScanResult {
+                    bssid: bss.bssid?,
+                    frequency: bss.freq.unwrap_or_default(),
+                    signal_level: bss.level.unwrap_or_default(),
+                    flags: bss.flags.unwrap_or_default(),
+                    ssid: bss.ssid.unwrap_or_default(),
+                })
+            })
+            .collect())
+    }
+
+    /// Send `BSS RANGE=<range>` with the fields selected by `mask`
+    fn bss_request(&mut self, range: &str, mask: BssMask) -> Result<Vec<Bss>> {
+        let mask = mask | BssMask::DELIM;
+        let reply = self.request_raw(&format!("BSS RANGE={} MASK=0x{:x}", range, mask.bits()))?;
+        if reply.starts_with("FAIL") {
+            return Err(Error::CommandFailed("BSS".into()));
+        }
+        Ok(Bss::parse_range(&reply)?)
+    }
+}
+
+impl<T: Transport> ClientAttached<T> {
+    /// Scan for access points, wait for the scan to finish and fetch its results
+    ///
+    /// If a scan is already running, this waits for it to finish and starts
+    /// another one. Control interface messages other than the end of the scan
+    /// stay buffered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// for bss in wpa.scan_and_wait(Duration::from_secs(10)).unwrap() {
+    ///     println!("{} {}", bss.bssid, bss.ssid);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - The scan could not be started or failed
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Parse`] - Malformed scan result
+    /// * [`Error::ScanBusy`] - Another scan started before this one could
+    /// * [`Error::Timeout`] - The scan did not finish within `timeout`
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn scan_and_wait(&mut self, timeout: Duration) -> Result<Vec<ScanResult>> {
+        let deadline = self.now() + timeout;
+        let start = self.buffered();
+        let reply = self.request_raw("SCAN")?;
+        match check_scan(&reply) {
+            Err(Error::ScanBusy) => {
+                self.wait_scan_done(start, deadline)?;
+                let start = self.buffered();
+                let reply = self.request_raw("SCAN")?;
+                check_scan(&reply)?;
+                self.wait_scan_done(start, deadline)?;
+            }
+            r => {
+                r?;
+                self.wait_scan_done(start, deadline)?;
+            }
+        }
+        let reply = self.request_raw("SCAN_RESULTS")?;
+        Ok(ScanResult::parse_table(&reply)?)
+    }
+
+    /// Wait for the end of a scan, ignoring the first `start` buffered messages
+    fn wait_scan_done(&mut self, start: usize, deadline: Instant) -> Result<()> {
+        let msg = self.wait_for_from(
+            start,
+            |msg| {
+                let msg = event::strip_priority(msg);
+                msg.starts_with("CTRL-EVENT-SCAN-RESULTS") || msg.starts_with("CTRL-EVENT-SCAN-FAILED")
+            },
+            deadline.saturating_duration_since(self.now()),
+        )?;
+        if event::strip_priority(&msg).starts_with("CTRL-EVENT-SCAN-FAILED") {
+            return Err(Error::CommandFailed("SCAN".into()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transport::mock_ctrl;
+
+    #[test]
+    fn bss_range() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply(
+            "id=0\nbssid=00:11:22:33:44:55\nlevel=-40\nssid=home\n====\n\
+             id=3\nbssid=66:77:88:99:aa:bb\nlevel=-71\nssid=guest\nsnr=20\n====\n",
+        );
+        let bss = wpa.bss_range(0, 10, BssMask::ID | BssMask::BSSID | BssMask::LEVEL | BssMask::SSID).unwrap();
+        assert_eq!(mock.sent(), vec!["BSS RANGE=0-10 MASK=0x21083"]);
+        assert_eq!(bss.len(), 2);
+        assert_eq!(bss[0].bssid, Some(Bssid::from([0x00, 0x11, 0x22, 0x33, 0x44, 0x55])));
+        assert_eq!(bss[1].id, Some(3));
+        assert_eq!(bss[1].level, Some(-71));
+        assert_eq!(bss[1].freq, None);
+        assert_eq!(bss[1].extra.get("snr").map(String::as_str), Some("20"));
+    }
+
+    #[test]
+    fn bss_range_empty() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("");
+        assert!(wpa.bss_range(0, 10, BssMask::ALL).unwrap().is_empty());
+    }
+
+    #[test]
+    fn scan_results_fresh() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply(
+            "bssid=00:11:22:33:44:55\nfreq=2412\nlevel=-40\nage=3\nflags=[ESS]\nssid=home\n====\n\
+             bssid=66:77:88:99:aa:bb\nfreq=5180\nlevel=-71\nage=120\nflags=[ESS]\nssid=gone\n====\n",
+        );
+        let results = wpa.scan_results_fresh(Duration::from_secs(30)).unwrap();
+        assert_eq!(mock.sent(), vec!["BSS RANGE=ALL MASK=0x21a86"]);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].ssid, "home");
+        assert_eq!(results[0].frequency, 2412);
+        assert_eq!(results[0].signal_level, -40);
+    }
+
+    const SCAN_RESULTS: &str = "bssid / frequency / signal level / flags / ssid\n\
+        00:11:22:33:44:55\t2412\t-71\t[WPA2-PSK-CCMP][ESS]\thome\n\
+        66:77:88:99:aa:bb\t5180\t-48\t[WPA2-PSK-CCMP][ESS]\thome\n\
+        cc:dd:ee:ff:00:11\t2437\t-55\t[ESS]\tcafe\twifi\n";
+
+    #[test]
+    fn scan_results_sorted() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply(SCAN_RESULTS);
+        let results = wpa.scan_results_sorted().unwrap();
+        let levels: Vec<_> = results.iter().map(|bss| bss.signal_level).collect();
+        assert_eq!(levels, vec![-48, -55, -71]);
+        assert_eq!(results[1].ssid, "cafe\twifi");
+    }
+
+    #[test]
+    fn security() {
+        for (flags, security) in [
+            ("[ESS]", Security::Open),
+            ("[WEP][ESS]", Security::Wep),
+            ("[WPA-PSK-TKIP][ESS]", Security::WpaPsk),
+            ("[WPA-PSK-CCMP+TKIP][WPA2-PSK-CCMP+TKIP][ESS][WPS]", Security::Wpa2Psk),
+            ("[WPA2-PSK+SAE-CCMP][ESS]", Security::Wpa2Wpa3),
+            ("[RSN-SAE-CCMP][ESS]", Security::Wpa3Sae),
+            ("[WPA2-OWE-CCMP][ESS]", Security::Owe),
+            ("[WPA2-FT/EAP-CCMP][ESS]", Security::Enterprise),
+        ] {
+            assert_eq!(SecurityFlags::parse(flags).security(), security, "{}", flags);
+        }
+        let flags = SecurityFlags::parse("[WPA2-PSK-CCMP][ESS][WPS-PBC]");
+        assert_eq!(
+            flags,
+            SecurityFlags::WPA2 | SecurityFlags::PSK | SecurityFlags::ESS | SecurityFlags::WPS
+        );
+        assert!(SecurityFlags::parse("[RSN-SAE-CCMP]").contains(SecurityFlags::WPA3));
+        assert_eq!(SecurityFlags::parse("[P2P][bogus]"), SecurityFlags::default());
+    }
+
+    #[test]
+    fn track_across_scans() {
+        let mut seen = HashMap::new();
+        for bss in ScanResult::parse_table(SCAN_RESULTS).unwrap() {
+            seen.insert(bss.bssid, bss);
+        }
+        let rescan = ScanResult::parse_table(SCAN_RESULTS).unwrap();
+        assert_eq!(seen.len(), 3);
+        assert_eq!(seen[&rescan[0].bssid], rescan[0]);
+    }
+
+    #[test]
+    fn best_bss_for_ssid() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply(SCAN_RESULTS);
+        mock.push_reply(SCAN_RESULTS);
+        assert_eq!(
+            wpa.best_bss_for_ssid("home").unwrap().unwrap().bssid.to_string(),
+            "66:77:88:99:aa:bb"
+        );
+        assert!(wpa.best_bss_for_ssid("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn scan() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        mock.push_reply("FAIL-BUSY\n");
+        mock.push_reply("FAIL\n");
+        wpa.scan().unwrap();
+        assert!(matches!(wpa.scan(), Err(Error::ScanBusy)));
+        assert!(matches!(wpa.scan(), Err(Error::CommandFailed(_))));
+    }
+
+    #[test]
+    fn scan_and_wait_busy() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        // A stale end of scan from before the request must not be mistaken for ours
+        mock.push_reply("<3>CTRL-EVENT-SCAN-RESULTS ");
+        mock.push_reply("PONG\n");
+        wpa.request("PING").unwrap();
+        mock.push_reply("FAIL-BUSY\n");
+        mock.push_reply("<3>CTRL-EVENT-SCAN-RESULTS ");
+        mock.push_reply("OK\n");
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        mock.push_reply("<3>CTRL-EVENT-SCAN-RESULTS ");
+        mock.push_reply(SCAN_RESULTS);
+        let results = wpa.scan_and_wait(Duration::from_millis(10)).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(mock.sent(), vec!["ATTACH", "PING", "SCAN", "SCAN", "SCAN_RESULTS"]);
+        assert_eq!(
+            wpa.recv_all().unwrap(),
+            vec!["<3>CTRL-EVENT-SCAN-RESULTS ", "<3>CTRL-EVENT-SCAN-STARTED "]
+        );
+    }
+}
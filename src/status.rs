@@ -0,0 +1,638 @@
+#![deny(missing_docs)]
+use super::Result;
+use std::collections::HashMap;
+
+use crate::bssid::Bssid;
+use crate::error::{Error, ParseError, ParseResult};
+use crate::transport::Transport;
+use crate::wpactrl::{Client, ClientAttached};
+
+/// The `key=value` lines of a reply, skipping empty lines
+fn key_values(reply: &str) -> impl Iterator<Item = ParseResult<(&str, &str)>> {
+    reply.lines().filter(|line| !line.is_empty()).map(|line| {
+        line.split_once('=')
+            .ok_or_else(|| ParseError::new(format!("invalid line {:?}", line)))
+    })
+}
+
+fn parse_field<F: std::str::FromStr>(key: &str, value: &str) -> ParseResult<F> {
+    value
+        .parse()
+        .map_err(|_| ParseError::new(format!("invalid {} {:?}", key, value)))
+}
+
+/// The kind of daemon behind a control interface, see [`Client::detect_mode`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// `wpa_supplicant`, managing a station
+    Supplicant,
+    /// `hostapd`, managing an access point
+    Hostapd,
+}
+
+/// The state of an interface, as reported by the `STATUS` command
+///
+/// Fields are `None` when missing from the reply, eg while disconnected.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Status {
+    /// Connection state, eg `COMPLETED`, `SCANNING` or `DISCONNECTED`
+    pub wpa_state: Option<String>,
+    /// Access point the station is associated with
+    pub bssid: Option<Bssid>,
+    /// Network name
+    pub ssid: Option<String>,
+    /// Frequency in MHz
+    pub freq: Option<u32>,
+    /// Id of the configured network in use
+    pub id: Option<u32>,
+    /// `id_str` of the configured network in use
+    pub id_str: Option<String>,
+    /// Key management in use, eg `WPA2-PSK`
+    pub key_mgmt: Option<String>,
+    /// IP address of the interface
+    pub ip_address: Option<String>,
+    /// Hardware address of the interface
+    pub address: Option<Bssid>,
+    /// Remaining `key=value` fields without a dedicated member
+    pub extra: HashMap<String, String>,
+}
+
+impl Status {
+    /// Parse the reply of a `STATUS` command
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::Status;
+    /// let status = Status::parse("wpa_state=COMPLETED\nssid=home\n").unwrap();
+    /// assert_eq!(status.ssid.as_deref(), Some("home"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`ParseError`] - Malformed line or value
+    pub fn parse(reply: &str) -> ParseResult<Self> {
+        let mut status = Self::default();
+        for key_value in key_values(reply) {
+            let (key, value) = key_value?;
+            match key {
+                "wpa_state" => status.wpa_state = Some(value.to_owned()),
+                "bssid" => status.bssid = Some(value.parse()?),
+                "ssid" => status.ssid = Some(value.to_owned()),
+                "freq" => status.freq = Some(parse_field(key, value)?),
+                "id" => status.id = Some(parse_field(key, value)?),
+                "id_str" => status.id_str = Some(value.to_owned()),
+                "key_mgmt" => status.key_mgmt = Some(value.to_owned()),
+                "ip_address" => status.ip_address = Some(value.to_owned()),
+                "address" => status.address = Some(value.parse()?),
+                _ => {
+                    status.extra.insert(key.to_owned(), value.to_owned());
+                }
+            }
+        }
+        Ok(status)
+    }
+}
+
+/// The global configuration of `wpa_supplicant`, as reported by the `DUMP` command
+///
+/// Fields are `None` when missing from the reply.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GlobalConfig {
+    /// ISO 3166 country code setting the regulatory domain, eg `US`
+    pub country: Option<String>,
+    /// Whether `SAVE_CONFIG` may write the configuration file
+    pub update_config: Option<bool>,
+    /// Device name used by Wi-Fi Direct and WPS
+    pub device_name: Option<String>,
+    /// Remaining `key=value` fields without a dedicated member
+    pub extra: HashMap<String, String>,
+}
+
+impl GlobalConfig {
+    /// Parse the reply of a `DUMP` command
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::GlobalConfig;
+    /// let config = GlobalConfig::parse("update_config=1\ncountry=DE\nap_scan=1\n").unwrap();
+    /// assert_eq!(config.country.as_deref(), Some("DE"));
+    /// assert_eq!(config.update_config, Some(true));
+    /// assert_eq!(config.extra["ap_scan"], "1");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`ParseError`] - Malformed line or value
+    pub fn parse(reply: &str) -> ParseResult<Self> {
+        let mut config = Self::default();
+        for key_value in key_values(reply) {
+            let (key, value) = key_value?;
+            match key {
+                "country" => config.country = Some(value.to_owned()),
+                "update_config" => config.update_config = Some(parse_field::<u8>(key, value)? != 0),
+                "device_name" => config.device_name = Some(value.to_owned()),
+                _ => {
+                    config.extra.insert(key.to_owned(), value.to_owned());
+                }
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// The current link, combining the `STATUS` and `SIGNAL_POLL` commands
+///
+/// Signal fields are `None` when the station is not connected.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LinkInfo {
+    /// Network name
+    pub ssid: Option<String>,
+    /// Access point the station is associated with
+    pub bssid: Option<Bssid>,
+    /// Hardware address of the interface
+    pub address: Option<Bssid>,
+    /// Signal strength in dBm
+    pub rssi: Option<i32>,
+    /// Transmit rate in Mbit/s
+    pub link_speed: Option<u32>,
+    /// Noise level in dBm, if the driver reports it
+    pub noise: Option<i32>,
+    /// Frequency in MHz
+    pub frequency: Option<u32>,
+}
+
+impl LinkInfo {
+    /// Add the fields of a `SIGNAL_POLL` reply
+    fn parse_signal_poll(&mut self, reply: &str) -> ParseResult<()> {
+        for key_value in key_values(reply) {
+            let (key, value) = key_value?;
+            match key {
+                "RSSI" => self.rssi = Some(parse_field(key, value)?),
+                "LINKSPEED" => self.link_speed = Some(parse_field(key, value)?),
+                // 9999 means the driver does not report noise
+                "NOISE" if value == "9999" => self.noise = None,
+                "NOISE" => self.noise = Some(parse_field(key, value)?),
+                "FREQUENCY" => self.frequency = Some(parse_field(key, value)?),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A station associated with an access point, as reported by hostapd's `STA-FIRST` / `STA-NEXT`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Station {
+    /// Hardware address of the station
+    pub addr: Bssid,
+    /// Association id
+    pub aid: Option<u32>,
+    /// State flags, eg `[AUTH][ASSOC][AUTHORIZED]`
+    pub flags: Option<String>,
+    /// Signal strength in dBm
+    pub signal: Option<i32>,
+    /// Milliseconds since the station was last active
+    pub inactive_msec: Option<u64>,
+    /// Seconds since the station connected
+    pub connected_time: Option<u64>,
+    /// Bytes received from the station
+    pub rx_bytes: Option<u64>,
+    /// Bytes sent to the station
+    pub tx_bytes: Option<u64>,
+    /// Remaining `key=value` fields without a dedicated member
+    pub extra: HashMap<String, String>,
+}
+
+impl Station {
+    /// Parse the reply of a `STA`, `STA-FIRST` or `STA-NEXT` command
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::Station;
+    /// let station = Station::parse("00:11:22:33:44:55\naid=1\nsignal=-48\n").unwrap();
+    /// assert_eq!(station.aid, Some(1));
+    /// assert_eq!(station.signal, Some(-48));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`ParseError`] - Missing address, or malformed line or value
+    pub fn parse(reply: &str) -> ParseResult<Self> {
+        let (addr, rest) = reply.split_once('\n').unwrap_or((reply, ""));
+        let mut station = Self {
+            addr: addr.parse()?,
+            ..Self::default()
+        };
+        for key_value in key_values(rest) {
+            let (key, value) = key_value?;
+            match key {
+                "aid" => station.aid = Some(parse_field(key, value)?),
+                "flags" => station.flags = Some(value.to_owned()),
+                "signal" => station.signal = Some(parse_field(key, value)?),
+                "inactive_msec" => station.inactive_msec = Some(parse_field(key, value)?),
+                "connected_time" => station.connected_time = Some(parse_field(key, value)?),
+                "rx_bytes" => station.rx_bytes = Some(parse_field(key, value)?),
+                "tx_bytes" => station.tx_bytes = Some(parse_field(key, value)?),
+                _ => {
+                    station.extra.insert(key.to_owned(), value.to_owned());
+                }
+            }
+        }
+        Ok(station)
+    }
+}
+
+/// Parse the reply of `STATUS`
+fn parse_status(reply: &str) -> Result<Status> {
+    if reply.starts_with("FAIL") {
+        return Err(Error::CommandFailed("STATUS".into()));
+    }
+    Ok(Status::parse(reply)?)
+}
+
+impl<T: Transport> ClientAttached<T> {
+    /// Like [`Client::status`], buffering messages received meanwhile
+    pub(crate) fn status(&mut self) -> Result<Status> {
+        let reply = self.request_raw("STATUS")?;
+        parse_status(&reply)
+    }
+}
+
+impl<T: Transport> Client<T> {
+    /// Query the state of the interface
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// println!("{:?}", wpa.status().unwrap().wpa_state);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (`FAIL`) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Parse`] - Malformed reply
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn status(&mut self) -> Result<Status> {
+        let reply = self.request_raw("STATUS")?;
+        parse_status(&reply)
+    }
+
+    /// Find out whether the control interface belongs to `wpa_supplicant` or `hostapd`
+    ///
+    /// Both answer `STATUS`, but only `wpa_supplicant` reports `wpa_state`,
+    /// while `hostapd` reports the interface `state`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::Mode;
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// if wpa.detect_mode().unwrap() == Mode::Supplicant {
+    ///     wpa.scan().unwrap();
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (`FAIL`) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Parse`] - Neither state reported
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn detect_mode(&mut self) -> Result<Mode> {
+        let reply = self.request_raw("STATUS")?;
+        if reply.starts_with("FAIL") {
+            return Err(Error::CommandFailed("STATUS".into()));
+        }
+        for key_value in key_values(&reply) {
+            match key_value?.0 {
+                "wpa_state" => return Ok(Mode::Supplicant),
+                "state" => return Ok(Mode::Hostapd),
+                _ => {}
+            }
+        }
+        Err(ParseError::new("no state in STATUS reply").into())
+    }
+
+    /// Read a snapshot of the global configuration with `DUMP`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// println!("{:?}", wpa.dump().unwrap().country);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (`FAIL`) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Parse`] - Malformed reply
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::UnknownCommand`] - `wpa_supplicant` built without `DUMP`, or `hostapd`
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn dump(&mut self) -> Result<GlobalConfig> {
+        let reply = self.request_raw("DUMP")?;
+        if reply.starts_with("UNKNOWN COMMAND") {
+            return Err(Error::UnknownCommand("DUMP".into()));
+        }
+        if reply.starts_with("FAIL") {
+            return Err(Error::CommandFailed("DUMP".into()));
+        }
+        Ok(GlobalConfig::parse(&reply)?)
+    }
+
+    /// Whether the station has completed connecting to a network
+    ///
+    /// This is `wpa_state=COMPLETED` in the reply to `STATUS`. Failing to query
+    /// the state is an error rather than `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// if !wpa.is_connected().unwrap() {
+    ///     wpa.request("RECONNECT").unwrap();
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (`FAIL`) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Parse`] - Malformed reply
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn is_connected(&mut self) -> Result<bool> {
+        Ok(self.status()?.wpa_state.as_deref() == Some("COMPLETED"))
+    }
+
+    /// Query the network, access point and signal of the current link
+    ///
+    /// This issues `STATUS` followed by `SIGNAL_POLL`. When the station is not
+    /// connected, `SIGNAL_POLL` fails and the signal fields are left empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// let link = wpa.signal_info().unwrap();
+    /// println!("{:?} {:?} dBm", link.ssid, link.rssi);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (`FAIL`) response to `STATUS`
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Parse`] - Malformed reply
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn signal_info(&mut self) -> Result<LinkInfo> {
+        let status = self.status()?;
+        let mut link = LinkInfo {
+            ssid: status.ssid,
+            bssid: status.bssid,
+            address: status.address,
+            frequency: status.freq,
+            ..LinkInfo::default()
+        };
+        let reply = self.request_raw("SIGNAL_POLL")?;
+        if !reply.starts_with("FAIL") {
+            link.parse_signal_poll(&reply)?;
+        }
+        Ok(link)
+    }
+
+    /// Ask for `CTRL-EVENT-SIGNAL-CHANGE` when the signal crosses a threshold
+    ///
+    /// The threshold is set midway between `above` and `below` (in dBm), and
+    /// after an event the signal must move past `above` or `below` before the
+    /// next one. This maps to `SIGNAL_MONITOR THRESHOLD=.. HYSTERESIS=..`, and
+    /// needs a driver supporting connection quality monitoring.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.set_signal_threshold(-65, -75).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (non-OK) response, eg not connected or unsupported driver
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn set_signal_threshold(&mut self, above: i32, below: i32) -> Result<()> {
+        // The difference of two i32 may not fit in an i32
+        let hysteresis = above.abs_diff(below) / 2;
+        let threshold = i64::from(above.min(below)) + i64::from(hysteresis);
+        self.request_ok(&format!("SIGNAL_MONITOR THRESHOLD={} HYSTERESIS={}", threshold, hysteresis))
+    }
+
+    /// Iterate over the stations associated with a hostapd access point
+    ///
+    /// Each station is requested as the iterator advances, with `STA-FIRST`
+    /// then `STA-NEXT`, so stopping early saves walking the whole list.
+    /// Iteration ends after the last station or the first error.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let mut hostapd = wpactrl::Client::builder()
+    ///             .ctrl_path("/var/run/hostapd/wlan0")
+    ///             .open()
+    ///             .unwrap();
+    /// for station in hostapd.stations_iter() {
+    ///     println!("{}", station.unwrap().addr);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Each item may fail with:
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (`FAIL`) response, eg the previous station left
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Parse`] - Malformed reply
+    /// * [`Error::Timeout`] - No reply within the client's timeout
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::UnknownCommand`] - Not an access point, or hostapd built without station listing
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn stations_iter(&mut self) -> impl Iterator<Item = Result<Station>> + '_ {
+        let mut cmd = Some("STA-FIRST".to_owned());
+        std::iter::from_fn(move || {
+            let sent = cmd.take()?;
+            let verb = sent.split(' ').next().unwrap_or_default().to_owned();
+            let reply = match self.request_raw(&sent) {
+                Ok(reply) => reply,
+                Err(e) => return Some(Err(e)),
+            };
+            // An empty reply follows the last station
+            if reply.is_empty() {
+                return None;
+            }
+            if reply.starts_with("UNKNOWN COMMAND") {
+                return Some(Err(Error::UnknownCommand(verb)));
+            }
+            if reply.starts_with("FAIL") {
+                return Some(Err(Error::CommandFailed(verb)));
+            }
+            let station = Station::parse(&reply).map_err(Error::from);
+            if let Ok(ref station) = station {
+                cmd = Some(format!("STA-NEXT {}", station.addr));
+            }
+            Some(station)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transport::mock_ctrl;
+
+    #[test]
+    fn signal_info() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply(
+            "bssid=00:11:22:33:44:55\nfreq=2412\nssid=home\nid=0\nmode=station\n\
+             wpa_state=COMPLETED\naddress=66:77:88:99:aa:bb\n",
+        );
+        mock.push_reply("RSSI=-52\nLINKSPEED=72\nNOISE=9999\nFREQUENCY=2412\n");
+        let link = wpa.signal_info().unwrap();
+        assert_eq!(link.ssid.as_deref(), Some("home"));
+        assert_eq!(link.bssid, Some(Bssid::from([0x00, 0x11, 0x22, 0x33, 0x44, 0x55])));
+        assert_eq!(link.rssi, Some(-52));
+        assert_eq!(link.link_speed, Some(72));
+        assert_eq!(link.noise, None);
+        assert_eq!(link.frequency, Some(2412));
+        mock.push_reply("wpa_state=COMPLETED\nmode=station\n");
+        assert_eq!(wpa.status().unwrap().extra["mode"], "station");
+        assert_eq!(mock.sent(), vec!["STATUS", "SIGNAL_POLL", "STATUS"]);
+    }
+
+    #[test]
+    fn detect_mode() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("bssid=00:11:22:33:44:55\nwpa_state=COMPLETED\n");
+        mock.push_reply("state=ENABLED\nphy=phy0\nfreq=2412\nbss[0]=wlan0\n");
+        mock.push_reply("freq=2412\n");
+        assert_eq!(wpa.detect_mode().unwrap(), Mode::Supplicant);
+        assert_eq!(wpa.detect_mode().unwrap(), Mode::Hostapd);
+        assert!(matches!(wpa.detect_mode(), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn dump() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("ctrl_interface=DIR=/var/run/wpa_supplicant\nupdate_config=0\ndevice_name=kiosk\n");
+        mock.push_reply("UNKNOWN COMMAND\n");
+        let config = wpa.dump().unwrap();
+        assert_eq!(config.update_config, Some(false));
+        assert_eq!(config.device_name.as_deref(), Some("kiosk"));
+        assert_eq!(config.country, None);
+        assert_eq!(config.extra["ctrl_interface"], "DIR=/var/run/wpa_supplicant");
+        assert!(matches!(wpa.dump(), Err(Error::UnknownCommand(_))));
+    }
+
+    #[test]
+    fn is_connected() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("wpa_state=COMPLETED\nssid=home\n");
+        mock.push_reply("wpa_state=ASSOCIATING\n");
+        mock.push_error(std::io::ErrorKind::ConnectionRefused);
+        assert!(wpa.is_connected().unwrap());
+        assert!(!wpa.is_connected().unwrap());
+        assert!(matches!(wpa.is_connected(), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn set_signal_threshold() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        mock.push_reply("FAIL\n");
+        wpa.set_signal_threshold(-65, -75).unwrap();
+        assert!(matches!(
+            wpa.set_signal_threshold(-75, -65),
+            Err(Error::CommandFailed(ref cmd)) if cmd == "SIGNAL_MONITOR"
+        ));
+        assert_eq!(mock.sent(), vec!["SIGNAL_MONITOR THRESHOLD=-70 HYSTERESIS=5"; 2]);
+
+        mock.push_reply("OK\n");
+        wpa.set_signal_threshold(i32::MAX, i32::MIN).unwrap();
+        assert_eq!(mock.sent()[2], "SIGNAL_MONITOR THRESHOLD=-1 HYSTERESIS=2147483647");
+    }
+
+    #[test]
+    fn stations_iter() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("00:11:22:33:44:55\naid=1\nflags=[AUTH][ASSOC][AUTHORIZED]\nsignal=-40\n");
+        mock.push_reply("66:77:88:99:aa:bb\naid=2\nconnected_time=30\nmode=ht\n");
+        mock.push_reply("");
+        let stations: Vec<_> = wpa.stations_iter().collect::<Result<_>>().unwrap();
+        assert_eq!(stations.len(), 2);
+        assert_eq!(stations[0].flags.as_deref(), Some("[AUTH][ASSOC][AUTHORIZED]"));
+        assert_eq!(stations[0].signal, Some(-40));
+        assert_eq!(stations[1].connected_time, Some(30));
+        assert_eq!(stations[1].extra["mode"], "ht");
+        assert_eq!(
+            mock.sent(),
+            vec!["STA-FIRST", "STA-NEXT 00:11:22:33:44:55", "STA-NEXT 66:77:88:99:aa:bb"]
+        );
+
+        mock.push_reply("00:11:22:33:44:55\naid=1\n");
+        assert_eq!(wpa.stations_iter().next().unwrap().unwrap().aid, Some(1));
+        assert_eq!(mock.sent().len(), 4);
+
+        mock.push_reply("UNKNOWN COMMAND\n");
+        let mut stations = wpa.stations_iter();
+        assert!(matches!(stations.next(), Some(Err(Error::UnknownCommand(ref cmd))) if cmd == "STA-FIRST"));
+        assert!(stations.next().is_none());
+    }
+
+    #[test]
+    fn stations_iter_boundaries() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("");
+        assert!(wpa.stations_iter().next().is_none());
+        assert_eq!(mock.sent(), vec!["STA-FIRST"]);
+
+        mock.push_reply("00:11:22:33:44:55\naid=1\n");
+        mock.push_reply("");
+        let stations: Vec<_> = wpa.stations_iter().collect::<Result<_>>().unwrap();
+        assert_eq!(stations.len(), 1);
+        assert_eq!(stations[0].addr, Bssid::from([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]));
+        assert_eq!(mock.sent()[1..], ["STA-FIRST", "STA-NEXT 00:11:22:33:44:55"]);
+        assert_eq!(mock.queued(), 0);
+    }
+
+    #[test]
+    fn signal_info_disconnected() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("wpa_state=DISCONNECTED\naddress=66:77:88:99:aa:bb\n");
+        mock.push_reply("FAIL\n");
+        let link = wpa.signal_info().unwrap();
+        assert_eq!(link.ssid, None);
+        assert_eq!(link.rssi, None);
+    }
+}
@@ -0,0 +1,320 @@
+#![deny(missing_docs)]
+use super::Result;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixDatagram;
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+
+/// A datagram channel used to exchange messages with `wpa_supplicant` / `hostapd`
+///
+/// [`UnixDatagram`] is the default implementation, and [`UdpTransport`] is used
+/// for remote control interfaces. Other implementations may be handed to
+/// [`Client::from_transport`](crate::Client::from_transport).
+pub trait Transport {
+    /// Send a single datagram, returning the number of bytes written
+    ///
+    /// # Errors
+    ///
+    /// Any I/O error raised by the underlying channel
+    fn send(&mut self, buf: &[u8]) -> std::io::Result<usize>;
+
+    /// Receive a single datagram into `buf`, returning its length
+    ///
+    /// # Errors
+    ///
+    /// Any I/O error raised by the underlying channel
+    fn recv(&mut self, buf: &mut [u8]) -> std::io::Result<usize>;
+
+    /// Wait up to `timeout` for a datagram to become available, or indefinitely if `None`
+    ///
+    /// The default implementation uses `select` on [`Transport::as_raw_fd`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Wait`] - Failed to wait on the underlying descriptor
+    fn pending(&mut self, timeout: Option<Duration>) -> Result<bool> {
+        select(self.as_raw_fd(), timeout)
+    }
+
+    /// The raw file descriptor of the underlying channel
+    fn as_raw_fd(&self) -> RawFd;
+}
+
+impl Transport for UnixDatagram {
+    fn send(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        UnixDatagram::send(self, buf)
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        UnixDatagram::recv(self, buf)
+    }
+
+    fn as_raw_fd(&self) -> RawFd {
+        AsRawFd::as_raw_fd(self)
+    }
+}
+
+/// How long to wait for the cookie when opening a [`UdpTransport`]
+const COOKIE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A UDP control interface, as built with `CONFIG_CTRL_IFACE=udp`
+///
+/// `wpa_supplicant` / `hostapd` only accept UDP commands prefixed with a
+/// cookie, which is requested when connecting and then added to every
+/// datagram sent.
+///
+/// # Examples
+///
+/// ```
+/// let transport = wpactrl::UdpTransport::connect("127.0.0.1:9877".parse().unwrap()).unwrap();
+/// let mut wpa = wpactrl::Client::from_transport(transport);
+/// ```
+#[derive(Debug)]
+pub struct UdpTransport {
+    socket: UdpSocket,
+    cookie: Vec<u8>,
+}
+
+impl UdpTransport {
+    /// Connect to the control interface at `addr` and fetch its cookie
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - No cookie in the reply to `GET_COOKIE`
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply to `GET_COOKIE`
+    /// * [`Error::Wait`] - Failed to wait on underlying UDP socket
+    pub fn connect(addr: SocketAddr) -> Result<Self> {
+        let socket = match addr {
+            SocketAddr::V4(_) => UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?,
+            SocketAddr::V6(_) => UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0))?,
+        };
+        socket.connect(addr)?;
+        socket.send(b"GET_COOKIE")?;
+        if !select(socket.as_raw_fd(), Some(COOKIE_TIMEOUT))? {
+            return Err(Error::Timeout);
+        }
+        let mut buf = [0; 64];
+        let len = socket.recv(&mut buf)?;
+        let cookie = buf[..len].strip_suffix(b"\n").unwrap_or(&buf[..len]);
+        if !cookie.starts_with(b"COOKIE=") {
+            return Err(Error::CommandFailed("GET_COOKIE".into()));
+        }
+        Ok(Self {
+            socket,
+            cookie: cookie.to_vec(),
+        })
+    }
+
+    /// The underlying UDP socket
+    #[must_use]
+    pub fn socket(&self) -> &UdpSocket {
+        &self.socket
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut datagram = Vec::with_capacity(self.cookie.len() + 1 + buf.len());
+        datagram.extend_from_slice(&self.cookie);
+        datagram.push(b' ');
+        datagram.extend_from_slice(buf);
+        let len = self.socket.send(&datagram)?;
+        Ok(len.saturating_sub(self.cookie.len() + 1))
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.socket.recv(buf)
+    }
+
+    fn as_raw_fd(&self) -> RawFd {
+        AsRawFd::as_raw_fd(&self.socket)
+    }
+}
+
+/// Wait for `fd` to become readable, for at most `timeout` or indefinitely if `None`
+///
+/// Timeouts too long to represent are treated as indefinite.
+pub(crate) fn select(fd: RawFd, timeout: Option<Duration>) -> Result<bool> {
+    let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+    loop {
+        let mut timeval = deadline.map(|deadline| {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            libc::timeval {
+                tv_sec: remaining.as_secs().try_into().unwrap_or(libc::time_t::MAX),
+                // Always below 1_000_000, so this never truncates
+                tv_usec: remaining.subsec_micros() as libc::suseconds_t,
+            }
+        });
+        let timeval_ptr = timeval
+            .as_mut()
+            .map_or(std::ptr::null_mut(), |timeval| timeval as *mut libc::timeval);
+        let r = unsafe {
+            let mut raw_fd_set = {
+                let mut raw_fd_set = std::mem::MaybeUninit::<libc::fd_set>::uninit();
+                libc::FD_ZERO(raw_fd_set.as_mut_ptr());
+                raw_fd_set.assume_init()
+            };
+            libc::FD_SET(fd, &mut raw_fd_set);
+            libc::select(
+                fd + 1,
+                &mut raw_fd_set,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                timeval_ptr,
+            )
+        };
+
+        if r >= 0 {
+            return Ok(r > 0);
+        }
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            return Err(Error::Wait(err));
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+pub use self::mock::MockTransport;
+
+/// A client on a fresh [`MockTransport`], along with a handle to script it
+#[cfg(test)]
+pub(crate) fn mock_ctrl() -> (MockTransport, crate::Client<MockTransport>) {
+    let mock = MockTransport::new();
+    (mock.clone(), crate::Client::from_transport(mock))
+}
+
+#[cfg(any(test, feature = "test-util"))]
+mod mock {
+    use super::{Result, Transport};
+    use std::collections::VecDeque;
+    use std::os::unix::io::RawFd;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[derive(Debug, Default)]
+    struct MockState {
+        incoming: VecDeque<std::io::Result<Vec<u8>>>,
+        sent: Vec<Vec<u8>>,
+    }
+
+    /// A scripted, in-memory [`Transport`] for testing without `wpa_supplicant`
+    ///
+    /// Clones share the same state, so a test can keep one handle while the
+    /// client owns another.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::{Client, MockTransport};
+    /// let mock = MockTransport::new();
+    /// mock.push_reply("PONG\n");
+    /// let mut wpa = Client::from_transport(mock.clone());
+    /// assert_eq!(wpa.request("PING").unwrap(), "PONG\n");
+    /// assert_eq!(mock.sent(), vec!["PING"]);
+    /// ```
+    #[derive(Clone, Debug, Default)]
+    pub struct MockTransport(Arc<Mutex<MockState>>);
+
+    impl MockTransport {
+        /// Create a transport with no queued datagrams
+        #[must_use]
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Queue a datagram (command reply or `<N>`-prefixed event) to be received
+        pub fn push_reply<B: AsRef<[u8]>>(&self, reply: B) {
+            self.0.lock().unwrap().incoming.push_back(Ok(reply.as_ref().to_vec()));
+        }
+
+        /// Queue an I/O error to be returned by the next receive
+        pub fn push_error(&self, kind: std::io::ErrorKind) {
+            self.0.lock().unwrap().incoming.push_back(Err(kind.into()));
+        }
+
+        /// Every datagram sent so far, lossily decoded as UTF8
+        #[must_use]
+        pub fn sent(&self) -> Vec<String> {
+            self.0
+                .lock()
+                .unwrap()
+                .sent
+                .iter()
+                .map(|s| String::from_utf8_lossy(s).into_owned())
+                .collect()
+        }
+
+        /// Number of queued datagrams and errors that have not been received yet
+        #[must_use]
+        pub fn queued(&self) -> usize {
+            self.0.lock().unwrap().incoming.len()
+        }
+    }
+
+    impl Transport for MockTransport {
+        fn send(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().sent.push(buf.to_vec());
+            Ok(buf.len())
+        }
+
+        fn recv(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.lock().unwrap().incoming.pop_front() {
+                Some(Ok(datagram)) => {
+                    let len = datagram.len().min(buf.len());
+                    buf[..len].copy_from_slice(&datagram[..len]);
+                    Ok(len)
+                }
+                Some(Err(e)) => Err(e),
+                None => Err(std::io::ErrorKind::WouldBlock.into()),
+            }
+        }
+
+        fn pending(&mut self, _timeout: Option<Duration>) -> Result<bool> {
+            Ok(!self.0.lock().unwrap().incoming.is_empty())
+        }
+
+        fn as_raw_fd(&self) -> RawFd {
+            -1
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn select_timeouts() {
+        let (a, b) = UnixDatagram::pair().unwrap();
+        let fd = AsRawFd::as_raw_fd(&a);
+        assert!(!select(fd, Some(Duration::from_millis(0))).unwrap());
+        b.send(b"PONG\n").unwrap();
+        assert!(select(fd, None).unwrap());
+        assert!(select(fd, Some(Duration::MAX)).unwrap());
+    }
+
+    #[test]
+    fn udp_cookie() {
+        let server = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut buf = [0; 64];
+            let (len, peer) = server.recv_from(&mut buf).unwrap();
+            assert_eq!(&buf[..len], b"GET_COOKIE");
+            server.send_to(b"COOKIE=0123abcd\n", peer).unwrap();
+            let len = server.recv(&mut buf).unwrap();
+            assert_eq!(&buf[..len], b"COOKIE=0123abcd PING");
+            server.send_to(b"PONG\n", peer).unwrap();
+        });
+        let mut transport = UdpTransport::connect(addr).unwrap();
+        assert_eq!(Transport::send(&mut transport, b"PING").unwrap(), 4);
+        let mut buf = [0; 64];
+        let len = Transport::recv(&mut transport, &mut buf).unwrap();
+        assert_eq!(&buf[..len], b"PONG\n");
+        handle.join().unwrap();
+    }
+}
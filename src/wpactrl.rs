@@ -1,18 +1,37 @@
 #![deny(missing_docs)]
 use super::Result;
 use log::warn;
+use std::borrow::Cow;
 use std::collections::VecDeque;
-use std::os::unix::io::{AsRawFd, RawFd};
+use std::fmt;
+use std::net::SocketAddr;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
 use std::os::unix::net::UnixDatagram;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, TrySendError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use crate::error::Error;
+use crate::bssid::Bssid;
+use crate::clock::{Clock, SystemClock};
+use crate::event::{self, Event};
+use crate::network;
+use crate::transport::{Transport, UdpTransport};
 
 const BUF_SIZE: usize = 10_240;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often the thread of [`ClientAttached::into_channel`] checks for a stop request
+const CHANNEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
 const PATH_DEFAULT_CLIENT: &str = "/tmp";
 const PATH_DEFAULT_SERVER: &str = "/var/run/wpa_supplicant/wlan0";
+const PATH_DEFAULT_CTRL_DIR: &str = "/var/run/wpa_supplicant";
+
+/// Observer of every command and reply, see [`ClientBuilder::on_exchange`]
+type ExchangeHook = Box<dyn Fn(&str, &str) + Send>;
 
 // Counter to avoid using the same file when creating multiple clients.
 static COUNTER: AtomicUsize = AtomicUsize::new(0);
@@ -22,6 +41,23 @@ static COUNTER: AtomicUsize = AtomicUsize::new(0);
 pub struct ClientBuilder {
     cli_path: Option<PathBuf>,
     ctrl_path: Option<PathBuf>,
+    ctrl_dir: Option<PathBuf>,
+    ifname: Option<String>,
+    lossy_utf8: bool,
+    strip_priority: bool,
+    trim_replies: bool,
+    recv_timeout: Option<Duration>,
+    control_timeout: Option<Duration>,
+    bind_name: Option<String>,
+    socket_mode: Option<u32>,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    autobind: bool,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    peer_uid: Option<libc::uid_t>,
+    blocking: bool,
+    terminate_on_drop: bool,
+    on_exchange: Option<ExchangeHook>,
+    clock: Option<Box<dyn Clock>>,
 }
 
 impl ClientBuilder {
@@ -47,301 +83,2263 @@ impl ClientBuilder {
         self
     }
 
-    /// A path-like object for the `wpa_supplicant` / `hostapd` UNIX domain sockets
+    /// An explicit file name for this application's UNIX domain socket
+    ///
+    /// The socket is created inside [`ClientBuilder::cli_path`]. By default a unique
+    /// `wpa_ctrl_<pid>-<counter>` name is generated, and a stale file with that name
+    /// is replaced. An explicit name is never replaced: opening fails if it is taken.
     ///
     /// # Examples
     ///
     /// ```
     /// use wpactrl::Client;
     /// let wpa = Client::builder()
-    ///             .ctrl_path("/var/run/wpa_supplicant/wlan0")
+    ///             .bind_name("my_app")
     ///             .open()
     ///             .unwrap();
     /// ```
     #[must_use]
-    pub fn ctrl_path<I, P>(mut self, ctrl_path: I) -> Self
+    pub fn bind_name<I, S>(mut self, bind_name: I) -> Self
     where
-        I: Into<Option<P>>,
-        P: AsRef<Path> + Sized,
-        PathBuf: From<P>,
+        I: Into<Option<S>>,
+        S: Into<String>,
     {
-        self.ctrl_path = ctrl_path.into().map(PathBuf::from);
+        self.bind_name = bind_name.into().map(Into::into);
         self
     }
 
-    /// Open a control interface to `wpa_supplicant` / `hostapd`.
+    /// File permissions for this application's UNIX domain socket, eg `0o600`
+    ///
+    /// By default the socket is created according to the process umask, which
+    /// may allow other users to send messages to it.
     ///
     /// # Examples
     ///
     /// ```
     /// use wpactrl::Client;
-    /// let wpa = Client::builder().open().unwrap();
+    /// let wpa = Client::builder()
+    ///             .socket_mode(0o600)
+    ///             .open()
+    ///             .unwrap();
     /// ```
-    /// # Errors
-    ///
-    /// * [[`Error::Io`]] - Low-level I/O error
-    pub fn open(self) -> Result<Client> {
-        let counter = COUNTER.fetch_add(1, Ordering::SeqCst);
-        let mut tries = 0;
-        loop {
-            tries += 1;
-            let bind_filename = format!("wpa_ctrl_{}-{}", std::process::id(), counter);
-            let bind_filepath = self
-                .cli_path
-                .as_deref()
-                .unwrap_or_else(|| Path::new(PATH_DEFAULT_CLIENT))
-                .join(bind_filename);
-            match UnixDatagram::bind(&bind_filepath) {
-                Ok(socket) => {
-                    socket.connect(self.ctrl_path.unwrap_or_else(|| PATH_DEFAULT_SERVER.into()))?;
-                    socket.set_nonblocking(true)?;
-                    return Ok(Client(ClientInternal {
-                        buffer: [0; BUF_SIZE],
-                        handle: socket,
-                        filepath: bind_filepath,
-                    }));
-                }
-                Err(ref e) if tries < 2 && e.kind() == std::io::ErrorKind::AddrInUse => {
-                    std::fs::remove_file(bind_filepath)?;
-                    continue;
-                }
-                Err(e) => return Err(e.into()),
-            };
-        }
-    }
-}
-
-struct ClientInternal {
-    buffer: [u8; BUF_SIZE],
-    handle: UnixDatagram,
-    filepath: PathBuf,
-}
-
-fn select(fd: RawFd, duration: Duration) -> Result<bool> {
-    let r = unsafe {
-        let mut raw_fd_set = {
-            let mut raw_fd_set = std::mem::MaybeUninit::<libc::fd_set>::uninit();
-            libc::FD_ZERO(raw_fd_set.as_mut_ptr());
-            raw_fd_set.assume_init()
-        };
-        libc::FD_SET(fd, &mut raw_fd_set);
-        libc::select(
-            fd + 1,
-            &mut raw_fd_set,
-            std::ptr::null_mut(),
-            std::ptr::null_mut(),
-            &mut libc::timeval {
-                tv_sec: duration.as_secs().try_into().unwrap(),
-                tv_usec: duration.subsec_micros().try_into().unwrap(),
-            },
-        )
-    };
-
-    if r >= 0 {
-        Ok(r > 0)
-    } else {
-        Err(Error::Wait)
-    }
-}
-
-impl ClientInternal {
-    /// Check if any messages are available
-    pub fn pending(&mut self) -> Result<bool> {
-        select(self.handle.as_raw_fd(), Duration::from_secs(0))
-    }
-
-    /// Receive a message
-    pub fn recv(&mut self) -> Result<Option<String>> {
-        if self.pending()? {
-            let buf_len = self.handle.recv(&mut self.buffer)?;
-            std::str::from_utf8(&self.buffer[0..buf_len])
-                .map(|s| Some(s.to_owned()))
-                .map_err(std::convert::Into::into)
-        } else {
-            Ok(None)
-        }
-    }
-
-    /// Send a command to `wpa_supplicant` / `hostapd`.
-    fn request<F: FnMut(&str)>(&mut self, cmd: &str, mut cb: F) -> Result<String> {
-        self.handle.send(cmd.as_bytes())?;
-        loop {
-            select(self.handle.as_raw_fd(), Duration::from_secs(10))?;
-            match self.handle.recv(&mut self.buffer) {
-                Ok(len) => {
-                    let s = std::str::from_utf8(&self.buffer[0..len])?;
-                    if s.starts_with('<') {
-                        cb(s);
-                    } else {
-                        return Ok(s.to_owned());
-                    }
-                }
-                Err(ref e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
-                Err(e) => return Err(e.into()),
-            }
-        }
-    }
-}
-
-impl Drop for ClientInternal {
-    fn drop(&mut self) {
-        if let Err(e) = std::fs::remove_file(&self.filepath) {
-            warn!("Unable to unlink {:?}", e);
-        }
+    #[must_use]
+    pub fn socket_mode<I>(mut self, socket_mode: I) -> Self
+    where
+        I: Into<Option<u32>>,
+    {
+        self.socket_mode = socket_mode.into();
+        self
     }
-}
-
-/// A connection to `wpa_supplicant` / `hostapd`
-pub struct Client(ClientInternal);
 
-impl Client {
-    /// Creates a builder for a `wpa_supplicant` / `hostapd` connection
+    /// Bind this application's socket to an abstract address picked by the kernel
+    ///
+    /// No file is created, so there is nothing to clean up and no need for a
+    /// writable [`ClientBuilder::cli_path`]. `cli_path`, `bind_name` and
+    /// `socket_mode` are ignored. Only available on Linux.
     ///
     /// # Examples
     ///
     /// ```
-    /// let wpa = wpactrl::Client::builder().open().unwrap();
+    /// use wpactrl::Client;
+    /// let wpa = Client::builder()
+    ///             .autobind()
+    ///             .open()
+    ///             .unwrap();
     /// ```
+    #[cfg(any(target_os = "linux", target_os = "android"))]
     #[must_use]
-    pub fn builder() -> ClientBuilder {
-        ClientBuilder::default()
+    pub fn autobind(mut self) -> Self {
+        self.autobind = true;
+        self
     }
 
-    /// Register as an event monitor for control interface messages
+    /// Only accept datagrams sent by a process running as `uid`
+    ///
+    /// This enables `SO_PASSCRED` and checks the credentials the kernel
+    /// attaches to every datagram received, so that another user bound to a
+    /// spoofed path in a shared directory cannot answer in place of
+    /// `wpa_supplicant` / `hostapd`. Datagrams from any other user, or without
+    /// credentials, fail with [`Error::UntrustedPeer`]. Disabled by default.
+    /// Only available on Linux.
+    ///
+    /// This only applies to Unix sockets: UDP datagrams carry no credentials,
+    /// so [`ClientBuilder::udp`] fails with [`Error::CredentialsUnavailable`]
+    /// rather than trusting every sender.
     ///
     /// # Examples
     ///
     /// ```
-    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
-    /// let wpa_attached = wpa.attach().unwrap();
+    /// use wpactrl::Client;
+    /// let wpa = Client::builder()
+    ///             .verify_peer(0)
+    ///             .open()
+    ///             .unwrap();
     /// ```
-    ///
-    /// # Errors
-    ///
-    /// * [`Error::Attach`] - Unexpected (non-OK) response
-    /// * [`Error::Io`] - Low-level I/O error
-    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
-    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
-    pub fn attach(mut self) -> Result<ClientAttached> {
-        // FIXME: None closure would be better
-        if self.0.request("ATTACH", |_: &str| ())? == "OK\n" {
-            Ok(ClientAttached(self.0, VecDeque::new()))
-        } else {
-            Err(Error::Attach)
-        }
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[must_use]
+    pub fn verify_peer<I: Into<Option<libc::uid_t>>>(mut self, uid: I) -> Self {
+        self.peer_uid = uid.into();
+        self
     }
 
-    /// Send a command to `wpa_supplicant` / `hostapd`.
+    /// A path-like object for the `wpa_supplicant` / `hostapd` UNIX domain sockets
     ///
-    /// Commands are generally identical to those used in `wpa_cli`,
-    /// except all uppercase (eg `LIST_NETWORKS`, `SCAN`, etc)
+    /// This may also be a directory holding the control sockets, such as
+    /// `/var/run/wpa_supplicant`, in which case the only socket inside it is used.
     ///
     /// # Examples
     ///
     /// ```
-    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
-    /// assert_eq!(wpa.request("PING").unwrap(), "PONG\n");
+    /// use wpactrl::Client;
+    /// let wpa = Client::builder()
+    ///             .ctrl_path("/var/run/wpa_supplicant/wlan0")
+    ///             .open()
+    ///             .unwrap();
     /// ```
+    #[must_use]
+    pub fn ctrl_path<I, P>(mut self, ctrl_path: I) -> Self
+    where
+        I: Into<Option<P>>,
+        P: AsRef<Path> + Sized,
+        PathBuf: From<P>,
+    {
+        self.ctrl_path = ctrl_path.into().map(PathBuf::from);
+        self
+    }
+
+    /// The network interface whose control socket to use, eg `wlan1`
     ///
-    /// # Errors
+    /// The socket is looked up as `<ctrl_dir>/<ifname>`, see [`ClientBuilder::ctrl_dir`].
+    /// An explicit [`ClientBuilder::ctrl_path`] takes precedence.
     ///
-    /// * [`Error::Io`] - Low-level I/O error
-    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
-    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
-    pub fn request(&mut self, cmd: &str) -> Result<String> {
-        self.0.request(cmd, |_: &str| ())
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::Client;
+    /// let wpa = Client::builder()
+    ///             .ifname("wlan0")
+    ///             .open()
+    ///             .unwrap();
+    /// ```
+    #[must_use]
+    pub fn ifname<I, S>(mut self, ifname: I) -> Self
+    where
+        I: Into<Option<S>>,
+        S: Into<String>,
+    {
+        self.ifname = ifname.into().map(Into::into);
+        self
     }
-}
-
-/// A connection to `wpa_supplicant` / `hostapd` that receives status messages
-pub struct ClientAttached(ClientInternal, VecDeque<String>);
 
-impl ClientAttached {
-    /// Stop listening for and discard any remaining control interface messages
+    /// The directory holding the control sockets, used with [`ClientBuilder::ifname`]
+    ///
+    /// Defaults to `/var/run/wpa_supplicant`.
     ///
     /// # Examples
     ///
     /// ```
-    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
-    /// wpa.detach().unwrap();
+    /// use wpactrl::Client;
+    /// let wpa = Client::builder()
+    ///             .ctrl_dir("/run/wpa_supplicant")
+    ///             .ifname("wlan0")
+    ///             .open()
+    ///             .unwrap();
     /// ```
+    #[must_use]
+    pub fn ctrl_dir<I, P>(mut self, ctrl_dir: I) -> Self
+    where
+        I: Into<Option<P>>,
+        P: AsRef<Path> + Sized,
+        PathBuf: From<P>,
+    {
+        self.ctrl_dir = ctrl_dir.into().map(PathBuf::from);
+        self
+    }
+
+    /// Replace invalid UTF8 sequences in messages instead of failing
     ///
-    /// # Errors
+    /// When enabled, string messages are decoded with [`String::from_utf8_lossy`]
+    /// and [`Error::Utf8ToStr`] is never returned. Disabled by default.
     ///
-    /// * [`Error::Detach`] - Unexpected (non-OK) response
-    /// * [`Error::Io`] - Low-level I/O error
-    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
-    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
-    pub fn detach(mut self) -> Result<Client> {
-        if self.0.request("DETACH", |_: &str| ())? == "OK\n" {
-            Ok(Client(self.0))
-        } else {
-            Err(Error::Detach)
-        }
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::Client;
+    /// let wpa = Client::builder()
+    ///             .lossy_utf8(true)
+    ///             .open()
+    ///             .unwrap();
+    /// ```
+    #[must_use]
+    pub fn lossy_utf8(mut self, lossy_utf8: bool) -> Self {
+        self.lossy_utf8 = lossy_utf8;
+        self
     }
 
-    /// Receive the next control interface message.
+    /// Whether received control interface messages lose their `<N>` priority prefix
     ///
-    /// Note that multiple control interface messages can be pending;
-    /// call this function repeatedly until it returns None to get all of them.
+    /// When enabled, every path returning messages as strings gives only the
+    /// message body, eg `CTRL-EVENT-SCAN-RESULTS ` instead of
+    /// `<3>CTRL-EVENT-SCAN-RESULTS `: [`ClientAttached::recv`],
+    /// [`ClientAttached::recv_blocking`], [`ClientAttached::recv_all`],
+    /// [`ClientAttached::wait_event`], [`ClientAttached::wait_for`], the
+    /// [`Iterator`] implementation of [`ClientAttached`] and the channel of
+    /// [`ClientAttached::into_channel`]. The predicate given to
+    /// [`ClientAttached::wait_for`] sees the same stripped form that is
+    /// returned. [`ClientAttached::recv_bytes`] is unaffected. Disabled by default.
     ///
     /// # Examples
     ///
     /// ```
-    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
-    /// assert_eq!(wpa.recv().unwrap(), None);
+    /// use wpactrl::Client;
+    /// let wpa = Client::builder()
+    ///             .strip_priority(true)
+    ///             .open()
+    ///             .unwrap();
     /// ```
+    #[must_use]
+    pub fn strip_priority(mut self, strip_priority: bool) -> Self {
+        self.strip_priority = strip_priority;
+        self
+    }
+
+    /// Whether a single trailing newline is removed from command replies
     ///
-    /// # Errors
+    /// When enabled, [`Client::request`] returns `PONG` instead of `PONG\n`.
+    /// Newlines inside multi-line replies are kept. Typed helpers such as
+    /// [`Client::status`] are unaffected. Disabled by default.
     ///
-    /// * [`Error::Io`] - Low-level I/O error
-    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
-    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
-    pub fn recv(&mut self) -> Result<Option<String>> {
-        if let Some(s) = self.1.pop_back() {
-            Ok(Some(s))
-        } else {
-            self.0.recv()
-        }
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::Client;
+    /// let mut wpa = Client::builder()
+    ///             .trim_replies(true)
+    ///             .open()
+    ///             .unwrap();
+    /// assert_eq!(wpa.request("PING").unwrap(), "PONG");
+    /// ```
+    #[must_use]
+    pub fn trim_replies(mut self, trim_replies: bool) -> Self {
+        self.trim_replies = trim_replies;
+        self
     }
 
-    /// Send a command to `wpa_supplicant` / `hostapd`.
-    ///
-    /// Commands are generally identical to those used in `wpa_cli`,
-    /// except all uppercase (eg `LIST_NETWORKS`, `SCAN`, etc)
+    /// How long [`ClientAttached::recv_blocking`] waits for a message
     ///
-    /// Control interface messages will be buffered as the command
-    /// runs, and will be returned on the next call to recv.
+    /// By default it waits indefinitely.
     ///
     /// # Examples
     ///
     /// ```
-    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
-    /// assert_eq!(wpa.request("PING").unwrap(), "PONG\n");
+    /// use std::time::Duration;
+    /// use wpactrl::Client;
+    /// let wpa = Client::builder()
+    ///             .recv_timeout(Duration::from_secs(30))
+    ///             .open()
+    ///             .unwrap();
     /// ```
+    #[must_use]
+    pub fn recv_timeout<I>(mut self, recv_timeout: I) -> Self
+    where
+        I: Into<Option<Duration>>,
+    {
+        self.recv_timeout = recv_timeout.into();
+        self
+    }
+
+    /// How long `ATTACH`, `DETACH` and `PING` wait for a reply
     ///
-    /// # Errors
+    /// These fail fast when `wpa_supplicant` / `hostapd` is wedged, while other
+    /// commands keep waiting up to 10 seconds. By default control commands wait
+    /// 10 seconds too.
     ///
-    /// * [`Error::Io`] - Low-level I/O error
-    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
-    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
-    pub fn request(&mut self, cmd: &str) -> Result<String> {
-        let mut messages = VecDeque::new();
-        let r = self.0.request(cmd, |s: &str| messages.push_front(s.into()));
-        self.1.extend(messages);
-        r
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use wpactrl::Client;
+    /// let wpa = Client::builder()
+    ///             .control_timeout(Duration::from_secs(1))
+    ///             .open()
+    ///             .unwrap();
+    /// ```
+    #[must_use]
+    pub fn control_timeout(mut self, control_timeout: Duration) -> Self {
+        self.control_timeout = Some(control_timeout);
+        self
     }
-}
 
-#[cfg(test)]
-mod test {
+    /// Whether the socket is put in non-blocking mode, which is the default
+    ///
+    /// When disabled, the socket stays blocking with `SO_RCVTIMEO` set to the
+    /// control timeout and `SO_SNDTIMEO` to the request timeout, and replies are
+    /// read with a plain `recv` instead of waiting in `select` first. This suits
+    /// simple synchronous programs. A request may wait up to the control timeout
+    /// past its own deadline, eg if [`ClientAttached::check_alive`] asks for less.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::Client;
+    /// let wpa = Client::builder()
+    ///             .nonblocking(false)
+    ///             .open()
+    ///             .unwrap();
+    /// ```
+    #[must_use]
+    pub fn nonblocking(mut self, nonblocking: bool) -> Self {
+        self.blocking = !nonblocking;
+        self
+    }
+
+    /// The time source for timeouts, [`SystemClock`] by default
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::{Client, SystemClock};
+    /// let wpa = Client::builder()
+    ///             .clock(SystemClock)
+    ///             .open()
+    ///             .unwrap();
+    /// ```
+    #[must_use]
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Some(Box::new(clock));
+        self
+    }
+
+    /// A function called with every command sent and the reply it received
+    ///
+    /// This allows recording command counts or latencies without wrapping
+    /// every call site. Control interface messages that arrive while waiting
+    /// for a reply are reported too, with an empty command. Invalid UTF8 is
+    /// replaced before calling `on_exchange`. Credentials such as `psk` or
+    /// `password` set with `SET_NETWORK` or read with `GET_NETWORK` are
+    /// replaced by `***`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::Client;
+    /// let wpa = Client::builder()
+    ///             .on_exchange(|cmd, reply| println!("{:?} -> {:?}", cmd, reply))
+    ///             .open()
+    ///             .unwrap();
+    /// ```
+    #[must_use]
+    pub fn on_exchange<F>(mut self, on_exchange: F) -> Self
+    where
+        F: Fn(&str, &str) + Send + 'static,
+    {
+        self.on_exchange = Some(Box::new(on_exchange));
+        self
+    }
+
+    /// Whether to send `TERMINATE` when the client is dropped, shutting down
+    /// `wpa_supplicant` / `hostapd`
+    ///
+    /// This lets test harnesses running a private supplicant clean up after
+    /// themselves. Failing to send `TERMINATE` is ignored. Disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::Client;
+    /// let wpa = Client::builder()
+    ///             .ctrl_path("/tmp/test_supplicant/wlan0")
+    ///             .terminate_on_drop(true)
+    ///             .open()
+    ///             .unwrap();
+    /// ```
+    #[must_use]
+    pub fn terminate_on_drop(mut self, terminate_on_drop: bool) -> Self {
+        self.terminate_on_drop = terminate_on_drop;
+        self
+    }
+
+    /// Open a control interface to `wpa_supplicant` / `hostapd`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::Client;
+    /// let wpa = Client::builder().open().unwrap();
+    /// ```
+    /// # Errors
+    ///
+    /// * [`Error::AmbiguousInterface`] - The control socket directory holds several sockets
+    /// * [`Error::CtrlPathMissing`] - Nothing exists at the control socket path
+    /// * [`Error::CtrlPathNotSocket`] - The control socket path is not a socket
+    /// * [`Error::InvalidPath`] - A socket path contains a NUL byte or is too long
+    /// * [[`Error::Io`]] - Low-level I/O error
+    /// * [`Error::PermissionDenied`] - Not allowed to access the control socket
+    pub fn open(mut self) -> Result<Client> {
+        let ctrl_path = match (self.ctrl_path.take(), self.ifname.take()) {
+            (Some(ctrl_path), _) => ctrl_path,
+            (None, Some(ifname)) => self
+                .ctrl_dir
+                .take()
+                .unwrap_or_else(|| PATH_DEFAULT_CTRL_DIR.into())
+                .join(ifname),
+            (None, None) => PATH_DEFAULT_SERVER.into(),
+        };
+        let ctrl_path = resolve_ctrl_path(ctrl_path)?;
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if self.autobind {
+            return self.connect(autobind()?, None, ctrl_path);
+        }
+        let cli_path = self
+            .cli_path
+            .as_deref()
+            .unwrap_or_else(|| Path::new(PATH_DEFAULT_CLIENT));
+        if let Some(ref bind_name) = self.bind_name {
+            let bind_filepath = cli_path.join(bind_name);
+            check_socket_path(&bind_filepath)?;
+            let socket = UnixDatagram::bind(&bind_filepath)?;
+            return self.connect(socket, Some(bind_filepath), ctrl_path);
+        }
+        let counter = COUNTER.fetch_add(1, Ordering::SeqCst);
+        let mut tries = 0;
+        loop {
+            tries += 1;
+            let bind_filename = format!("wpa_ctrl_{}-{}", std::process::id(), counter);
+            let bind_filepath = cli_path.join(bind_filename);
+            check_socket_path(&bind_filepath)?;
+            match UnixDatagram::bind(&bind_filepath) {
+                Ok(socket) => {
+                    return self.connect(socket, Some(bind_filepath), ctrl_path);
+                }
+                Err(ref e) if tries < 2 && e.kind() == std::io::ErrorKind::AddrInUse => {
+                    std::fs::remove_file(bind_filepath)?;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+        }
+    }
+
+    /// Open a UDP control interface to `wpa_supplicant` / `hostapd` at `addr`
+    ///
+    /// The UNIX socket options (`cli_path`, `bind_name`, `socket_mode`,
+    /// `ctrl_path`, `ctrl_dir` and `ifname`) are ignored, while
+    /// [`ClientBuilder::verify_peer`] cannot be honoured and is refused.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::Client;
+    /// let wpa = Client::builder()
+    ///             .udp("127.0.0.1:9877".parse().unwrap())
+    ///             .unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - No cookie in the reply to `GET_COOKIE`
+    /// * [`Error::CredentialsUnavailable`] - [`ClientBuilder::verify_peer`] was set
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply to `GET_COOKIE`
+    /// * [`Error::Wait`] - Failed to wait on underlying UDP socket
+    pub fn udp(mut self, addr: SocketAddr) -> Result<Client<UdpTransport>> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if self.peer_uid.is_some() {
+            return Err(Error::CredentialsUnavailable);
+        }
+        let transport = UdpTransport::connect(addr)?;
+        if self.blocking {
+            transport.socket().set_read_timeout(Some(self.read_timeout()))?;
+            transport.socket().set_write_timeout(Some(REQUEST_TIMEOUT))?;
+        } else {
+            transport.socket().set_nonblocking(true)?;
+        }
+        let mut internal = ClientInternal::new(transport, None);
+        self.apply(&mut internal);
+        Ok(Client(internal))
+    }
+
+    /// Connect a freshly bound socket to `wpa_supplicant` / `hostapd`
+    fn connect(mut self, socket: UnixDatagram, bind_filepath: Option<PathBuf>, ctrl_path: PathBuf) -> Result<Client> {
+        // Owning the bind file first ensures it is unlinked if connecting fails
+        let mut internal = ClientInternal::new(socket, bind_filepath);
+        if let Some(mode) = self.socket_mode {
+            if let Some(ref filepath) = internal.filepath {
+                std::fs::set_permissions(filepath, std::fs::Permissions::from_mode(mode))?;
+            }
+        }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if self.peer_uid.is_some() {
+            pass_credentials(&internal.handle)?;
+            internal.peer_uid = self.peer_uid;
+        }
+        internal.handle.connect(&ctrl_path).map_err(|e| access_error(e, &ctrl_path))?;
+        internal.ctrl_path = Some(ctrl_path);
+        if self.blocking {
+            internal.handle.set_read_timeout(Some(self.read_timeout()))?;
+            internal.handle.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+        } else {
+            internal.handle.set_nonblocking(true)?;
+        }
+        self.apply(&mut internal);
+        Ok(Client(internal))
+    }
+
+    /// `SO_RCVTIMEO` for blocking sockets
+    ///
+    /// Short enough for control commands, longer waits retry until their deadline.
+    fn read_timeout(&self) -> Duration {
+        self.control_timeout.unwrap_or(REQUEST_TIMEOUT).min(REQUEST_TIMEOUT)
+    }
+
+    /// Copy the settings shared by every transport to a freshly connected client
+    ///
+    /// This runs once the socket is fully set up, so that a failure before
+    /// then never sends `TERMINATE` because of [`ClientBuilder::terminate_on_drop`].
+    fn apply<T: Transport>(&mut self, internal: &mut ClientInternal<T>) {
+        internal.lossy_utf8 = self.lossy_utf8;
+        internal.strip_priority = self.strip_priority;
+        internal.trim_replies = self.trim_replies;
+        internal.recv_timeout = self.recv_timeout;
+        internal.control_timeout = self.control_timeout.unwrap_or(REQUEST_TIMEOUT);
+        internal.on_exchange = self.on_exchange.take();
+        if let Some(clock) = self.clock.take() {
+            internal.clock = clock;
+        }
+        internal.blocking = self.blocking;
+        internal.terminate_on_drop = self.terminate_on_drop;
+    }
+}
+
+/// Create a socket bound to a unique abstract address picked by the kernel
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn autobind() -> std::io::Result<UnixDatagram> {
+    use std::os::unix::io::AsRawFd;
+    let socket = UnixDatagram::unbound()?;
+    // An address made of the family alone asks the kernel to autobind
+    let r = unsafe {
+        let mut addr: libc::sockaddr_un = std::mem::zeroed();
+        addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        libc::bind(
+            AsRawFd::as_raw_fd(&socket),
+            &addr as *const libc::sockaddr_un as *const libc::sockaddr,
+            std::mem::size_of::<libc::sa_family_t>() as libc::socklen_t,
+        )
+    };
+    if r < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(socket)
+}
+
+/// Ask the kernel to attach the sender's credentials to every datagram received on `socket`
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn pass_credentials(socket: &UnixDatagram) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    let enable: libc::c_int = 1;
+    let r = unsafe {
+        libc::setsockopt(
+            AsRawFd::as_raw_fd(socket),
+            libc::SOL_SOCKET,
+            libc::SO_PASSCRED,
+            &enable as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if r < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Receive a datagram from `fd` into `buf`, returning its length and the user ID of its sender
+///
+/// The user ID is only known once `SO_PASSCRED` is enabled on `fd`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn recv_with_uid(fd: std::os::unix::io::RawFd, buf: &mut [u8]) -> std::io::Result<(usize, Option<libc::uid_t>)> {
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+    // Room for a single `SCM_CREDENTIALS` message, aligned for `cmsghdr`
+    let mut control = [0u64; 8];
+    let mut uid = None;
+    let len = unsafe {
+        let mut msg: libc::msghdr = std::mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = std::mem::size_of_val(&control) as _;
+        let len = libc::recvmsg(fd, &mut msg, 0);
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while len >= 0 && !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_CREDENTIALS {
+                let cred = std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::ucred);
+                uid = Some(cred.uid);
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+        len
+    };
+    if len < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok((len as usize, uid))
+}
+
+/// Make sure `ctrl_path` names an existing socket, or a directory holding exactly one
+fn resolve_ctrl_path(ctrl_path: PathBuf) -> Result<PathBuf> {
+    check_socket_path(&ctrl_path)?;
+    match std::fs::metadata(&ctrl_path) {
+        Ok(metadata) if metadata.file_type().is_socket() => Ok(ctrl_path),
+        Ok(metadata) if metadata.is_dir() => {
+            let mut sockets = Vec::new();
+            let entries = std::fs::read_dir(&ctrl_path).map_err(|e| access_error(e, &ctrl_path))?;
+            for entry in entries {
+                let entry = entry?;
+                if entry.file_type()?.is_socket() {
+                    sockets.push(entry.path());
+                }
+            }
+            sockets.sort();
+            match sockets.len() {
+                0 => Err(Error::CtrlPathMissing(ctrl_path)),
+                1 => {
+                    let socket = sockets.remove(0);
+                    check_socket_path(&socket)?;
+                    Ok(socket)
+                }
+                _ => Err(Error::AmbiguousInterface(sockets)),
+            }
+        }
+        Ok(_) => Err(Error::CtrlPathNotSocket(ctrl_path)),
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err(Error::CtrlPathMissing(ctrl_path))
+        }
+        Err(e) => Err(access_error(e, &ctrl_path)),
+    }
+}
+
+/// Make sure `path` fits in a Unix socket address
+///
+/// The OS would otherwise reject it with an opaque `EINVAL`, or `ENAMETOOLONG`
+/// for paths longer than `sun_path`, which commonly happens under long
+/// temporary directories.
+fn check_socket_path(path: &Path) -> Result<()> {
+    let bytes = path.as_os_str().as_bytes();
+    if bytes.contains(&0) {
+        return Err(Error::InvalidPath(path.to_path_buf(), "contains a NUL byte"));
+    }
+    // One byte of `sun_path` is kept for the terminating NUL
+    let addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+    if bytes.len() >= addr.sun_path.len() {
+        return Err(Error::InvalidPath(path.to_path_buf(), "too long for a Unix socket address"));
+    }
+    Ok(())
+}
+
+/// `e`, as [`Error::PermissionDenied`] if it means access to `path` was refused
+fn access_error(e: std::io::Error, path: &Path) -> Error {
+    if e.kind() == std::io::ErrorKind::PermissionDenied {
+        Error::PermissionDenied(path.to_path_buf())
+    } else {
+        e.into()
+    }
+}
+
+/// Whether `e` means the other end of the control socket no longer exists
+fn peer_gone(e: &std::io::Error) -> bool {
+    matches!(e.kind(), std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::NotFound)
+}
+
+/// Whether `msg` is an event whose priority is below `min_priority`
+fn below_priority(msg: &[u8], min_priority: u8) -> bool {
+    min_priority > 0 && event::priority(msg).is_some_and(|priority| priority < min_priority)
+}
+
+struct ClientInternal<T: Transport> {
+    buffer: [u8; BUF_SIZE],
+    /// Length of the last datagram received, the only valid part of `buffer`
+    received_len: usize,
+    handle: T,
+    filepath: Option<PathBuf>,
+    ctrl_path: Option<PathBuf>,
+    lossy_utf8: bool,
+    strip_priority: bool,
+    trim_replies: bool,
+    ifname: Option<String>,
+    recv_timeout: Option<Duration>,
+    control_timeout: Duration,
+    attached: bool,
+    blocking: bool,
+    min_priority: u8,
+    unanswered: usize,
+    terminate_on_drop: bool,
+    on_exchange: Option<ExchangeHook>,
+    clock: Box<dyn Clock>,
+    /// Length of the reply to the last command, while it is still in `buffer`
+    last_reply_len: Option<usize>,
+    /// The only user allowed to send datagrams, if checked
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    peer_uid: Option<libc::uid_t>,
+}
+
+fn decode(buf: &[u8], lossy_utf8: bool) -> Result<Cow<'_, str>> {
+    if lossy_utf8 {
+        Ok(String::from_utf8_lossy(buf))
+    } else {
+        Ok(Cow::Borrowed(std::str::from_utf8(buf)?))
+    }
+}
+
+impl<T: Transport> ClientInternal<T> {
+    fn new(handle: T, filepath: Option<PathBuf>) -> Self {
+        Self {
+            buffer: [0; BUF_SIZE],
+            received_len: 0,
+            handle,
+            filepath,
+            ctrl_path: None,
+            lossy_utf8: false,
+            strip_priority: false,
+            trim_replies: false,
+            ifname: None,
+            recv_timeout: None,
+            control_timeout: REQUEST_TIMEOUT,
+            attached: false,
+            blocking: false,
+            min_priority: 0,
+            unanswered: 0,
+            terminate_on_drop: false,
+            on_exchange: None,
+            clock: Box::new(SystemClock),
+            last_reply_len: None,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            peer_uid: None,
+        }
+    }
+
+    /// Check if any messages are available
+    pub fn pending(&mut self) -> Result<bool> {
+        self.handle.pending(Some(Duration::from_secs(0)))
+    }
+
+    /// Receive a datagram into the buffer, returning its length
+    ///
+    /// A datagram from an unexpected user is consumed, but fails with [`Error::UntrustedPeer`].
+    fn recv_datagram(&mut self) -> Result<usize> {
+        // Nothing in the buffer is valid until the receive succeeds
+        self.received_len = 0;
+        self.last_reply_len = None;
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if let Some(peer_uid) = self.peer_uid {
+            let (len, uid) = recv_with_uid(self.handle.as_raw_fd(), &mut self.buffer)?;
+            if uid != Some(peer_uid) {
+                return Err(Error::UntrustedPeer(uid));
+            }
+            self.received_len = len;
+            return Ok(len);
+        }
+        self.received_len = self.handle.recv(&mut self.buffer)?;
+        Ok(self.received_len)
+    }
+
+    /// The reply to the last command, if no datagram was received since
+    fn last_reply(&self) -> Option<Cow<'_, str>> {
+        self.last_reply_len.map(|len| String::from_utf8_lossy(self.received(len)))
+    }
+
+    /// The first `len` bytes of the last datagram received
+    ///
+    /// # Panics
+    ///
+    /// If `len` goes past the end of the datagram, which would expose stale bytes
+    fn received(&self, len: usize) -> &[u8] {
+        assert!(len <= self.received_len, "read of {} bytes past a {} byte datagram", len, self.received_len);
+        &self.buffer[0..len]
+    }
+
+    /// Whether the datagram of length `len` in the buffer is an event below `min_priority`
+    fn filtered(&self, len: usize) -> bool {
+        below_priority(self.received(len), self.min_priority)
+    }
+
+    /// Whether a datagram of length `len` filled the whole buffer, and so was likely cut short
+    fn truncated(&self, len: usize) -> bool {
+        if len < self.buffer.len() {
+            return false;
+        }
+        warn!("Message of at least {} bytes truncated", len);
+        true
+    }
+
+    /// Receive a message as raw bytes
+    pub fn recv_bytes(&mut self) -> Result<Option<Vec<u8>>> {
+        while self.pending()? {
+            let buf_len = self.recv_datagram()?;
+            if !self.filtered(buf_len) {
+                if self.truncated(buf_len) {
+                    return Err(Error::Truncated);
+                }
+                return Ok(Some(self.received(buf_len).to_vec()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Receive a message
+    pub fn recv(&mut self) -> Result<Option<String>> {
+        while self.pending()? {
+            let buf_len = self.recv_datagram()?;
+            if !self.filtered(buf_len) {
+                if self.truncated(buf_len) {
+                    return Err(Error::Truncated);
+                }
+                return decode(self.received(buf_len), self.lossy_utf8).map(|s| Some(s.into_owned()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Wait until a message arrives or `recv_timeout` elapses
+    pub fn recv_blocking(&mut self) -> Result<String> {
+        self.recv_until(self.recv_timeout.map(|timeout| self.clock.now() + timeout))
+    }
+
+    /// Wait until a message arrives or `deadline` passes, if any
+    fn recv_until(&mut self, deadline: Option<Instant>) -> Result<String> {
+        loop {
+            let wait = deadline.map(|deadline| deadline.saturating_duration_since(self.clock.now()));
+            if self.handle.pending(wait)? {
+                match self.recv_datagram() {
+                    Ok(len) if self.filtered(len) => continue,
+                    Ok(len) if self.truncated(len) => return Err(Error::Truncated),
+                    Ok(len) => {
+                        return decode(self.received(len), self.lossy_utf8).map(Cow::into_owned)
+                    }
+                    Err(Error::Io(ref e))
+                        if e.kind() == std::io::ErrorKind::Interrupted
+                            || e.kind() == std::io::ErrorKind::WouldBlock =>
+                    {
+                        continue
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            if deadline.is_some_and(|deadline| self.clock.now() >= deadline) {
+                return Err(Error::Timeout);
+            }
+        }
+    }
+
+    /// Send a command to `wpa_supplicant` / `hostapd`.
+    fn request<F: FnMut(&str)>(&mut self, cmd: &str, cb: F) -> Result<String> {
+        self.request_timeout(cmd, REQUEST_TIMEOUT, cb)
+    }
+
+    /// Send a command to `wpa_supplicant` / `hostapd`, waiting at most `timeout` for the reply.
+    fn request_timeout<F: FnMut(&str)>(&mut self, cmd: &str, timeout: Duration, cb: F) -> Result<String> {
+        let len = self.request_len(cmd.as_bytes(), timeout, cb)?;
+        self.reply(len)
+    }
+
+    /// Send several commands in order, collecting their replies.
+    fn request_batch<F: FnMut(&str)>(&mut self, cmds: &[&str], mut cb: F) -> Result<Vec<String>> {
+        cmds.iter().map(|cmd| self.request(cmd, &mut cb)).collect()
+    }
+
+    /// Send a raw command to `wpa_supplicant` / `hostapd`, returning the raw reply.
+    fn request_bytes<F: FnMut(&str)>(&mut self, cmd: &[u8], cb: F) -> Result<Vec<u8>> {
+        let len = self.request_len(cmd, REQUEST_TIMEOUT, cb)?;
+        Ok(self.received(len).to_vec())
+    }
+
+    /// Decode the reply of length `len` held in the buffer
+    fn reply(&self, len: usize) -> Result<String> {
+        decode(self.received(len), self.lossy_utf8).map(Cow::into_owned)
+    }
+
+    /// A reply as returned by the public `request` methods, see [`ClientBuilder::trim_replies`]
+    fn trimmed(&self, mut reply: String) -> String {
+        if self.trim_replies && reply.ends_with('\n') {
+            reply.pop();
+        }
+        reply
+    }
+
+    /// Send a command addressed to the selected interface, returning the length of the reply
+    fn request_len<F: FnMut(&str)>(&mut self, cmd: &[u8], timeout: Duration, cb: F) -> Result<usize> {
+        match self.ifname {
+            Some(ref ifname) => {
+                let mut prefixed = format!("IFNAME={} ", ifname).into_bytes();
+                prefixed.extend_from_slice(cmd);
+                self.send_recv(&prefixed, timeout, cb)
+            }
+            None => self.send_recv(cmd, timeout, cb),
+        }
+    }
+
+    /// Send a command addressed to the selected interface without waiting for its reply
+    fn request_nowait(&mut self, cmd: &str) -> Result<()> {
+        match self.ifname {
+            Some(ref ifname) => self.handle.send(format!("IFNAME={} {}", ifname, cmd).as_bytes())?,
+            None => self.handle.send(cmd.as_bytes())?,
+        };
+        self.unanswered += 1;
+        Ok(())
+    }
+
+    /// Hand the event of length `len` in the buffer to `on_exchange` and `cb`, unless filtered
+    fn forward_event<F: FnMut(&str)>(&self, len: usize, cb: &mut F) -> Result<()> {
+        if !self.filtered(len) {
+            if let Some(ref on_exchange) = self.on_exchange {
+                on_exchange("", &String::from_utf8_lossy(self.received(len)));
+            }
+            cb(&decode(self.received(len), self.lossy_utf8)?);
+        }
+        Ok(())
+    }
+
+    /// Drop every datagram already queued, returning how many were dropped
+    fn flush(&mut self) -> Result<usize> {
+        let mut count = 0;
+        while self.pending()? {
+            self.recv_datagram()?;
+            count += 1;
+        }
+        self.unanswered = 0;
+        Ok(count)
+    }
+
+    /// Drop the replies to commands sent with `request_nowait` that have already arrived
+    ///
+    /// Events received in the meantime are handed to `cb`.
+    fn discard_unanswered<F: FnMut(&str)>(&mut self, cb: &mut F) -> Result<()> {
+        while self.unanswered > 0 && self.pending()? {
+            let len = self.recv_datagram()?;
+            if event::is_event(self.received(len)) {
+                self.forward_event(len, cb)?;
+            } else {
+                self.unanswered -= 1;
+            }
+        }
+        // Replies that have not arrived by now are assumed never to come
+        self.unanswered = 0;
+        Ok(())
+    }
+
+    /// Send a command exactly as given, leaving its reply at the start of the buffer
+    fn send_recv<F: FnMut(&str)>(&mut self, cmd: &[u8], timeout: Duration, mut cb: F) -> Result<usize> {
+        if self.unanswered > 0 {
+            self.discard_unanswered(&mut cb)?;
+        }
+        self.handle.send(cmd)?;
+        let deadline = self.clock.now() + timeout;
+        self.recv_reply(cmd, deadline, &mut cb)
+    }
+
+    /// Send every command before reading any reply, then read one reply per command, in order
+    ///
+    /// A reply that is truncated or not valid UTF8 only fails its own entry.
+    /// If waiting fails, the replies still due are discarded when they arrive.
+    fn pipeline<F: FnMut(&str)>(&mut self, cmds: &[&str], mut cb: F) -> Result<Vec<Result<String>>> {
+        if self.unanswered > 0 {
+            self.discard_unanswered(&mut cb)?;
+        }
+        let cmds: Vec<_> = cmds
+            .iter()
+            .map(|cmd| match self.ifname {
+                Some(ref ifname) => format!("IFNAME={} {}", ifname, cmd),
+                None => (*cmd).to_owned(),
+            })
+            .collect();
+        for (sent, cmd) in cmds.iter().enumerate() {
+            if let Err(e) = self.handle.send(cmd.as_bytes()) {
+                self.unanswered = sent;
+                return Err(e.into());
+            }
+        }
+        let mut replies = Vec::with_capacity(cmds.len());
+        for (i, cmd) in cmds.iter().enumerate() {
+            let deadline = self.clock.now() + REQUEST_TIMEOUT;
+            match self.recv_reply(cmd.as_bytes(), deadline, &mut cb) {
+                Ok(len) => replies.push(self.reply(len)),
+                Err(Error::Truncated) => replies.push(Err(Error::Truncated)),
+                Err(e) => {
+                    self.unanswered = cmds.len() - i;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(replies)
+    }
+
+    /// Wait until `deadline` for the reply to `cmd`, leaving it at the start of the buffer
+    ///
+    /// Events received in the meantime are handed to `cb`.
+    fn recv_reply<F: FnMut(&str)>(&mut self, cmd: &[u8], deadline: Instant, cb: &mut F) -> Result<usize> {
+        loop {
+            let remaining = deadline.saturating_duration_since(self.clock.now());
+            // A blocking socket waits in `recv` itself, bounded by `SO_RCVTIMEO`
+            let ready = if self.blocking {
+                !remaining.is_zero()
+            } else {
+                self.handle.pending(Some(remaining))?
+            };
+            if !ready {
+                return Err(Error::Timeout);
+            }
+            match self.recv_datagram() {
+                Ok(len) => {
+                    if event::is_event(self.received(len)) {
+                        // Failing here would leave the reply to be mistaken for the next one's
+                        if !self.truncated(len) {
+                            self.forward_event(len, cb)?;
+                        }
+                    } else if self.truncated(len) {
+                        return Err(Error::Truncated);
+                    } else {
+                        if let Some(ref on_exchange) = self.on_exchange {
+                            let cmd = String::from_utf8_lossy(cmd);
+                            let reply = String::from_utf8_lossy(self.received(len));
+                            let (cmd, reply) = network::redact(&cmd, &reply);
+                            on_exchange(&cmd, &reply);
+                        }
+                        self.last_reply_len = Some(len);
+                        return Ok(len);
+                    }
+                }
+                Err(Error::Io(ref e))
+                    if e.kind() == std::io::ErrorKind::Interrupted
+                        || e.kind() == std::io::ErrorKind::WouldBlock
+                        || (self.blocking && e.kind() == std::io::ErrorKind::TimedOut) =>
+                {
+                    continue
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl<T: Transport> Drop for ClientInternal<T> {
+    fn drop(&mut self) {
+        if self.terminate_on_drop {
+            // The other end may already be gone
+            let _ = self.handle.send(b"TERMINATE");
+        }
+        if let Some(filepath) = &self.filepath {
+            if let Err(e) = std::fs::remove_file(filepath) {
+                warn!("Unable to unlink {:?}", e);
+            }
+        }
+    }
+}
+
+/// A connection to `wpa_supplicant` / `hostapd`
+///
+/// The connection uses a [`UnixDatagram`] unless built with [`Client::from_transport`].
+pub struct Client<T: Transport = UnixDatagram>(ClientInternal<T>);
+
+impl<T: Transport> fmt::Debug for Client<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("ctrl_path", &self.0.ctrl_path)
+            .field("bind_path", &self.0.filepath)
+            .finish()
+    }
+}
+
+impl Client {
+    /// Creates a builder for a `wpa_supplicant` / `hostapd` connection
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let wpa = wpactrl::Client::builder().open().unwrap();
+    /// ```
+    #[must_use]
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    /// The underlying UNIX domain socket, eg to query socket options
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let wpa = wpactrl::Client::builder().open().unwrap();
+    /// println!("{:?}", wpa.socket().local_addr().unwrap());
+    /// ```
+    #[must_use]
+    pub fn socket(&self) -> &UnixDatagram {
+        &self.0.handle
+    }
+
+    /// The underlying UNIX domain socket, eg to tune socket options such as `SO_RCVBUF`
+    ///
+    /// Changing the non-blocking mode or connecting the socket elsewhere will
+    /// break the client.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::os::unix::io::AsRawFd;
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// let fd = wpa.socket_mut().as_raw_fd();
+    /// ```
+    pub fn socket_mut(&mut self) -> &mut UnixDatagram {
+        &mut self.0.handle
+    }
+
+    /// Send subsequent commands to another `wpa_supplicant` / `hostapd` control socket
+    ///
+    /// The socket is reconnected in place, keeping its bound address. As with
+    /// [`ClientBuilder::ctrl_path`], `ctrl_path` may be a directory holding a
+    /// single control socket.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.set_ctrl_path("/var/run/wpa_supplicant/wlan1").unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::AmbiguousInterface`] - The control socket directory holds several sockets
+    /// * [`Error::CtrlPathMissing`] - Nothing exists at the control socket path
+    /// * [`Error::CtrlPathNotSocket`] - The control socket path is not a socket
+    /// * [`Error::InvalidPath`] - The control socket path contains a NUL byte or is too long
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::PermissionDenied`] - Not allowed to access the control socket
+    pub fn set_ctrl_path<P: AsRef<Path>>(&mut self, ctrl_path: P) -> Result<()> {
+        let ctrl_path = resolve_ctrl_path(ctrl_path.as_ref().to_path_buf())?;
+        self.0.handle.connect(&ctrl_path).map_err(|e| access_error(e, &ctrl_path))?;
+        self.0.ctrl_path = Some(ctrl_path);
+        Ok(())
+    }
+}
+
+impl<T: Transport> Client<T> {
+    /// Creates a client on top of an already-connected [`Transport`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::os::unix::net::UnixDatagram;
+    /// let socket = UnixDatagram::bind("/tmp/wpa_ctrl_example").unwrap();
+    /// socket.connect("/var/run/wpa_supplicant/wlan0").unwrap();
+    /// let wpa = wpactrl::Client::from_transport(socket);
+    /// ```
+    #[must_use]
+    pub fn from_transport(transport: T) -> Self {
+        Self(ClientInternal::new(transport, None))
+    }
+
+    /// The reply to the last command, as received and before any parsing
+    ///
+    /// This helps diagnose an [`Error::Parse`] from a typed command such as
+    /// [`Client::status`]. The reply is only decoded when asked for, with
+    /// invalid UTF8 replaced, and is gone once another message is received.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// if let Err(e) = wpa.status() {
+    ///     eprintln!("{}, reply was {:?}", e, wpa.last_reply());
+    /// }
+    /// ```
+    #[must_use]
+    pub fn last_reply(&self) -> Option<Cow<'_, str>> {
+        self.0.last_reply()
+    }
+
+    /// Use `clock` instead of [`SystemClock`] for timeouts, see [`ClientBuilder::clock`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wpactrl::{Client, MockClock, MockTransport};
+    /// let wpa = Client::from_transport(MockTransport::new()).with_clock(MockClock::new());
+    /// ```
+    #[must_use]
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.0.clock = Box::new(clock);
+        self
+    }
+
+    /// Register as an event monitor for control interface messages
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// let wpa_attached = wpa.attach().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Attach`] - Unexpected (non-OK) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the control timeout
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn attach(self) -> Result<ClientAttached<T>> {
+        self.attach_with_buffer(VecDeque::new())
+    }
+
+    /// Register as an event monitor, starting with previously received messages
+    ///
+    /// The messages in `buffer`, such as those returned by
+    /// [`ClientAttached::detach_keeping_buffer`], are returned by recv before
+    /// any new message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// let (mut wpa, buffer) = wpa.detach_keeping_buffer().unwrap();
+    /// assert_eq!(wpa.request("PING").unwrap(), "PONG\n");
+    /// let wpa = wpa.attach_with_buffer(buffer).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Attach`] - Unexpected (non-OK) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the control timeout
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn attach_with_buffer(mut self, buffer: VecDeque<String>) -> Result<ClientAttached<T>> {
+        // FIXME: None closure would be better
+        let timeout = self.0.control_timeout;
+        if self.0.request_timeout("ATTACH", timeout, |_: &str| ())? == "OK\n" {
+            self.0.attached = true;
+            Ok(ClientAttached(self.0, buffer))
+        } else {
+            Err(Error::Attach)
+        }
+    }
+
+    /// Close the connection, removing the socket file it was bound to
+    ///
+    /// Dropping the client does the same, but can only log a failure to
+    /// remove the file. With [`ClientBuilder::terminate_on_drop`], `TERMINATE`
+    /// is still sent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.close().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Failed to remove the socket file
+    pub fn close(mut self) -> Result<()> {
+        match self.0.filepath.take() {
+            Some(filepath) => Ok(std::fs::remove_file(filepath)?),
+            None => Ok(()),
+        }
+    }
+
+    /// Check that `wpa_supplicant` / `hostapd` answers `PING`
+    ///
+    /// Waits at most [`ClientBuilder::control_timeout`] for the reply.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.ping().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::CommandFailed`] - Unexpected (non-PONG) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the control timeout
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn ping(&mut self) -> Result<()> {
+        let timeout = self.0.control_timeout;
+        if self.0.request_timeout("PING", timeout, |_: &str| ())? == "PONG\n" {
+            Ok(())
+        } else {
+            Err(Error::CommandFailed("PING".into()))
+        }
+    }
+
+    /// Send a command to `wpa_supplicant` / `hostapd`.
+    ///
+    /// Commands are generally identical to those used in `wpa_cli`,
+    /// except all uppercase (eg `LIST_NETWORKS`, `SCAN`, etc)
+    ///
+    /// Some commands answer with an empty datagram (eg `STA-NEXT` after the
+    /// last station), which is returned as an empty string. No answer at all
+    /// is reported as [`Error::Timeout`] instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// assert_eq!(wpa.request("PING").unwrap(), "PONG\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within 10 seconds
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn request(&mut self, cmd: &str) -> Result<String> {
+        let reply = self.request_raw(cmd)?;
+        Ok(self.0.trimmed(reply))
+    }
+
+    /// Send a command, returning its reply untouched by [`ClientBuilder::trim_replies`]
+    pub(crate) fn request_raw(&mut self, cmd: &str) -> Result<String> {
+        self.0.request(cmd, |_: &str| ())
+    }
+
+    /// Send a command to `wpa_supplicant` / `hostapd`, handing any control
+    /// interface message received before the reply to `cb`
+    ///
+    /// [`Client::request`] discards such messages. They still reach a client
+    /// that is not attached, eg when another client shares its bound address.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// let reply = wpa.request_with_events("PING", |msg| println!("event: {}", msg)).unwrap();
+    /// assert_eq!(reply, "PONG\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within 10 seconds
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn request_with_events<F: FnMut(&str)>(&mut self, cmd: &str, cb: F) -> Result<String> {
+        let reply = self.0.request(cmd, cb)?;
+        Ok(self.0.trimmed(reply))
+    }
+
+    /// Send a command to `wpa_supplicant` / `hostapd` without waiting for its reply
+    ///
+    /// This suits fire-and-forget commands such as `NOTE`, or commands sent
+    /// during shutdown. A reply that has arrived by the next request is
+    /// discarded; one arriving later would be mistaken for the reply to a
+    /// subsequent request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.request_nowait("NOTE starting test").unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    pub fn request_nowait(&mut self, cmd: &str) -> Result<()> {
+        self.0.request_nowait(cmd)
+    }
+
+    /// Discard every datagram waiting on the socket, returning how many were discarded
+    ///
+    /// Stale replies left over from a previous connection or from
+    /// [`Client::request_nowait`] would otherwise be mistaken for the reply to
+    /// the next request. This does not wait for datagrams still on their way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// wpa.flush().unwrap();
+    /// assert_eq!(wpa.request("PING").unwrap(), "PONG\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn flush(&mut self) -> Result<usize> {
+        self.0.flush()
+    }
+
+    /// Send a command to `wpa_supplicant` / `hostapd`, borrowing the reply from the receive buffer.
+    ///
+    /// This avoids allocating a `String` for every reply, and the reply stays
+    /// valid until the client is used again. The reply must be valid UTF8,
+    /// even with [`ClientBuilder::lossy_utf8`], since replacing invalid
+    /// sequences would need an allocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// assert_eq!(wpa.request_borrowed("PING").unwrap(), "PONG\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within 10 seconds
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn request_borrowed(&mut self, cmd: &str) -> Result<&str> {
+        let mut len = self.0.request_len(cmd.as_bytes(), REQUEST_TIMEOUT, |_: &str| ())?;
+        if self.0.trim_replies && self.0.received(len).ends_with(b"\n") {
+            len -= 1;
+        }
+        Ok(std::str::from_utf8(self.0.received(len))?)
+    }
+
+    /// Send several commands in order, returning their replies in the same order.
+    ///
+    /// Sending stops at the first command that fails. Replies are returned as
+    /// is, so a command answered with `FAIL` does not stop the batch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// let replies = wpa.request_batch(&["PING", "PING"]).unwrap();
+    /// assert_eq!(replies, vec!["PONG\n", "PONG\n"]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within 10 seconds
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn request_batch(&mut self, cmds: &[&str]) -> Result<Vec<String>> {
+        let replies = self.0.request_batch(cmds, |_: &str| ())?;
+        Ok(replies.into_iter().map(|reply| self.0.trimmed(reply)).collect())
+    }
+
+    /// Send several commands at once, then collect their replies in the same order
+    ///
+    /// Unlike [`Client::request_batch`], this does not wait for each reply
+    /// before sending the next command, which saves round trips on slow
+    /// links such as a [`UdpTransport`]. `wpa_supplicant` answers commands in
+    /// the order it receives them, and datagrams are not reordered on a Unix
+    /// socket or a local network. A reply that is too long or not valid UTF8
+    /// only fails its own entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// let replies = wpa.pipeline(&["PING", "PING"]).unwrap();
+    /// assert_eq!(replies[1].as_deref().unwrap(), "PONG\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within 10 seconds of the previous one
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn pipeline(&mut self, cmds: &[&str]) -> Result<Vec<Result<String>>> {
+        let replies = self.0.pipeline(cmds, |_: &str| ())?;
+        Ok(replies.into_iter().map(|reply| reply.map(|reply| self.0.trimmed(reply))).collect())
+    }
+
+    /// Address subsequent commands to a single interface of a multi-interface `wpa_supplicant`
+    ///
+    /// When connected to the global control interface, each command is prefixed
+    /// with `IFNAME=<ifname>`. Pass `None` to stop prefixing commands.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder()
+    ///             .ctrl_path("/var/run/wpa_supplicant-global")
+    ///             .open()
+    ///             .unwrap();
+    /// wpa.with_ifname("wlan1");
+    /// assert_eq!(wpa.request("PING").unwrap(), "PONG\n");
+    /// ```
+    pub fn with_ifname<'a, I>(&mut self, ifname: I)
+    where
+        I: Into<Option<&'a str>>,
+    {
+        self.0.ifname = ifname.into().map(str::to_owned);
+    }
+
+    /// Send a command to a single interface of a multi-interface `wpa_supplicant`.
+    ///
+    /// This overrides any interface selected with [`Client::with_ifname`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder()
+    ///             .ctrl_path("/var/run/wpa_supplicant-global")
+    ///             .open()
+    ///             .unwrap();
+    /// assert_eq!(wpa.request_iface("wlan1", "PING").unwrap(), "PONG\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within 10 seconds
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn request_iface(&mut self, ifname: &str, cmd: &str) -> Result<String> {
+        let cmd = format!("IFNAME={} {}", ifname, cmd);
+        let len = self.0.send_recv(cmd.as_bytes(), REQUEST_TIMEOUT, |_: &str| ())?;
+        let reply = self.0.reply(len)?;
+        Ok(self.0.trimmed(reply))
+    }
+
+    /// Send a raw command to `wpa_supplicant` / `hostapd`, returning the raw reply.
+    ///
+    /// Unlike [`Client::request`], neither the command nor the reply need to be valid UTF8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// assert_eq!(wpa.request_bytes(b"PING").unwrap(), b"PONG\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within 10 seconds
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted control interface message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn request_bytes(&mut self, cmd: &[u8]) -> Result<Vec<u8>> {
+        self.0.request_bytes(cmd, |_: &str| ())
+    }
+}
+
+/// A connection to `wpa_supplicant` / `hostapd` that receives status messages
+pub struct ClientAttached<T: Transport = UnixDatagram>(ClientInternal<T>, VecDeque<String>);
+
+impl<T: Transport> fmt::Debug for ClientAttached<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ClientAttached")
+            .field("ctrl_path", &self.0.ctrl_path)
+            .field("bind_path", &self.0.filepath)
+            .field("buffered", &self.1.len())
+            .finish()
+    }
+}
+
+impl ClientAttached {
+    /// The underlying UNIX domain socket, eg to query socket options
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// println!("{:?}", wpa.socket().local_addr().unwrap());
+    /// ```
+    #[must_use]
+    pub fn socket(&self) -> &UnixDatagram {
+        &self.0.handle
+    }
+
+    /// The underlying UNIX domain socket, eg to raise `SO_RCVBUF` so that
+    /// bursts of control interface messages are not dropped
+    ///
+    /// Changing the non-blocking mode or connecting the socket elsewhere will
+    /// break the client.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::os::unix::io::AsRawFd;
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// let fd = wpa.socket_mut().as_raw_fd();
+    /// ```
+    pub fn socket_mut(&mut self) -> &mut UnixDatagram {
+        &mut self.0.handle
+    }
+}
+
+impl<T: Transport> ClientAttached<T> {
+    /// Stop listening for and discard any remaining control interface messages
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// wpa.detach().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Detach`] - Unexpected (non-OK) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the control timeout
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn detach(self) -> Result<Client<T>> {
+        self.detach_keeping_buffer().map(|(client, _)| client)
+    }
+
+    /// Stop listening for control interface messages, returning those not received yet
+    ///
+    /// This includes messages that arrive before `wpa_supplicant` acknowledges
+    /// the `DETACH`. They can be handed back to [`Client::attach_with_buffer`]
+    /// so no message is lost while detached.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// let (wpa, buffer) = wpa.detach_keeping_buffer().unwrap();
+    /// for msg in &buffer {
+    ///     println!("{}", msg);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Detach`] - Unexpected (non-OK) response
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within the control timeout
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn detach_keeping_buffer(mut self) -> Result<(Client<T>, VecDeque<String>)> {
+        let timeout = self.0.control_timeout;
+        if self.0.request_timeout("DETACH", timeout, |s: &str| self.1.push_back(s.into()))? == "OK\n" {
+            self.0.attached = false;
+            Ok((Client(self.0), self.1))
+        } else {
+            Err(Error::Detach)
+        }
+    }
+
+    /// Receive the next control interface message.
+    ///
+    /// Note that multiple control interface messages can be pending;
+    /// call this function repeatedly until it returns None to get all of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// assert_eq!(wpa.recv().unwrap(), None);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Truncated`] - Message too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn recv(&mut self) -> Result<Option<String>> {
+        let msg = if let Some(s) = self.1.pop_front() {
+            Some(s)
+        } else {
+            let r = self.0.recv();
+            self.track(r)?
+        };
+        Ok(msg.map(|msg| self.output(msg)))
+    }
+
+    /// Wait for the next control interface message.
+    ///
+    /// Unlike [`ClientAttached::recv`], this blocks until a message arrives,
+    /// or until the timeout set with [`ClientBuilder::recv_timeout`] elapses.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// loop {
+    ///     println!("{}", wpa.recv_blocking().unwrap());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No message arrived before the timeout elapsed
+    /// * [`Error::Truncated`] - Message too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn recv_blocking(&mut self) -> Result<String> {
+        let msg = if let Some(s) = self.1.pop_front() {
+            s
+        } else {
+            let r = self.0.recv_blocking();
+            self.track(r)?
+        };
+        Ok(self.output(msg))
+    }
+
+    /// Wait at most `timeout` for the next control interface message
+    ///
+    /// Unlike [`ClientAttached::recv_blocking`], running out of time is not an
+    /// error: `None` is returned instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// if let Some(msg) = wpa.wait_event(Duration::from_secs(1)).unwrap() {
+    ///     println!("{}", msg);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Truncated`] - Message too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn wait_event(&mut self, timeout: Duration) -> Result<Option<String>> {
+        let msg = if let Some(s) = self.1.pop_front() {
+            s
+        } else {
+            let r = self.0.recv_until(Some(self.now() + timeout));
+            match self.track(r) {
+                Ok(msg) => msg,
+                Err(Error::Timeout) => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        };
+        Ok(Some(self.output(msg)))
+    }
+
+    /// Receive the next control interface message as raw bytes.
+    ///
+    /// Unlike [`ClientAttached::recv`], this never fails on messages that
+    /// are not valid UTF8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// assert_eq!(wpa.recv_bytes().unwrap(), None);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Truncated`] - Message too long for the receive buffer
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn recv_bytes(&mut self) -> Result<Option<Vec<u8>>> {
+        if let Some(s) = self.1.pop_front() {
+            Ok(Some(s.into_bytes()))
+        } else {
+            let r = self.0.recv_bytes();
+            self.track(r)
+        }
+    }
+
+    /// Receive every buffered and pending control interface message, oldest first
+    ///
+    /// This returns a snapshot of the messages available now without
+    /// waiting, so they can be processed without interleaving with socket reads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// for msg in wpa.recv_all().unwrap() {
+    ///     println!("{}", msg);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Truncated`] - Message too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn recv_all(&mut self) -> Result<Vec<String>> {
+        // Messages received so far stay buffered if a later one fails
+        loop {
+            let r = self.0.recv();
+            match self.track(r)? {
+                Some(msg) => self.1.push_back(msg),
+                None => break,
+            }
+        }
+        let msgs: Vec<_> = self.1.drain(..).collect();
+        Ok(msgs.into_iter().map(|msg| self.output(msg)).collect())
+    }
+
+    /// Send a command to `wpa_supplicant` / `hostapd`.
+    ///
+    /// Commands are generally identical to those used in `wpa_cli`,
+    /// except all uppercase (eg `LIST_NETWORKS`, `SCAN`, etc)
+    ///
+    /// Some commands answer with an empty datagram (eg `STA-NEXT` after the
+    /// last station), which is returned as an empty string. No answer at all
+    /// is reported as [`Error::Timeout`] instead.
+    ///
+    /// Control interface messages will be buffered as the command
+    /// runs, and will be returned on the next call to recv.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap();
+    /// assert_eq!(wpa.request("PING").unwrap(), "PONG\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within 10 seconds
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn request(&mut self, cmd: &str) -> Result<String> {
+        let reply = self.request_raw(cmd)?;
+        Ok(self.0.trimmed(reply))
+    }
+
+    /// Send a command, returning its reply untouched by [`ClientBuilder::trim_replies`]
+    pub(crate) fn request_raw(&mut self, cmd: &str) -> Result<String> {
+        let r = self.0.request(cmd, |s: &str| self.1.push_back(s.into()));
+        self.track(r)
+    }
+
+    /// Send a command to `wpa_supplicant` / `hostapd` without waiting for its reply
+    ///
+    /// A reply that has arrived by the next request is discarded, while
+    /// control interface messages are buffered as usual. See
+    /// [`Client::request_nowait`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// wpa.request_nowait("NOTE starting test").unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    pub fn request_nowait(&mut self, cmd: &str) -> Result<()> {
+        let r = self.0.request_nowait(cmd);
+        self.track(r)
+    }
+
+    /// Send several commands in order, returning their replies in the same order.
+    ///
+    /// Sending stops at the first command that fails. Control interface
+    /// messages arriving during the batch are buffered, and will be returned
+    /// on the next call to recv.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// let replies = wpa.request_batch(&["PING", "PING"]).unwrap();
+    /// assert_eq!(replies, vec!["PONG\n", "PONG\n"]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within 10 seconds
+    /// * [`Error::Truncated`] - Reply too long for the receive buffer
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn request_batch(&mut self, cmds: &[&str]) -> Result<Vec<String>> {
+        let r = self.0.request_batch(cmds, |s: &str| self.1.push_back(s.into()));
+        let replies = self.track(r)?;
+        Ok(replies.into_iter().map(|reply| self.0.trimmed(reply)).collect())
+    }
+
+    /// Send several commands at once, then collect their replies in the same order
+    ///
+    /// See [`Client::pipeline`]. Control interface messages arriving meanwhile
+    /// are buffered, and will be returned on the next call to recv.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// let replies = wpa.pipeline(&["PING", "PING"]).unwrap();
+    /// assert_eq!(replies[1].as_deref().unwrap(), "PONG\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No reply within 10 seconds of the previous one
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn pipeline(&mut self, cmds: &[&str]) -> Result<Vec<Result<String>>> {
+        let r = self.0.pipeline(cmds, |s: &str| self.1.push_back(s.into()));
+        let replies = self.track(r)?;
+        Ok(replies.into_iter().map(|reply| reply.map(|reply| self.0.trimmed(reply))).collect())
+    }
+
+    /// Check that `wpa_supplicant` / `hostapd` still answers on the control interface
+    ///
+    /// Sends `PING` and waits up to `timeout` for `PONG`. Control interface
+    /// messages arriving in the meantime are buffered for [`ClientAttached::recv`].
+    /// Returns false if there is no reply in time or the other end of the
+    /// control socket has gone away.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// assert!(wpa.check_alive(Duration::from_secs(1)).unwrap());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn check_alive(&mut self, timeout: Duration) -> Result<bool> {
+        let r = self.0.request_timeout("PING", timeout, |s: &str| self.1.push_back(s.into()));
+        match self.track(r) {
+            Ok(reply) => Ok(reply == "PONG\n"),
+            Err(Error::Timeout) => Ok(false),
+            Err(Error::Io(ref e)) if peer_gone(e) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Wait for a control interface message matching `pred`, for at most `timeout`
+    ///
+    /// Buffered messages are checked first, oldest first. Messages that do not
+    /// match stay buffered in order, and will be returned on the next call to recv.
+    /// `pred` sees each message as it would be returned, so without its `<N>`
+    /// prefix when [`ClientBuilder::strip_priority`] is enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// wpa.request("WPS_PBC").unwrap();
+    /// let msg = wpa
+    ///     .wait_for(|msg| msg.contains("WPS-SUCCESS") || msg.contains("WPS-FAIL"), Duration::from_secs(120))
+    ///     .unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - No matching message within `timeout`
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn wait_for<F: Fn(&str) -> bool>(&mut self, pred: F, timeout: Duration) -> Result<String> {
+        self.wait_for_from(0, pred, timeout)
+    }
+
+    /// Like [`ClientAttached::wait_for`], ignoring the first `start` buffered messages
+    pub(crate) fn wait_for_from<F: Fn(&str) -> bool>(
+        &mut self,
+        start: usize,
+        pred: F,
+        timeout: Duration,
+    ) -> Result<String> {
+        // The predicate sees messages exactly as they would be returned
+        let found = self.1.iter().skip(start).position(|msg| pred(self.output_str(msg)));
+        if let Some(msg) = found.and_then(|i| self.1.remove(start + i)) {
+            return Ok(self.output(msg));
+        }
+        let deadline = self.now() + timeout;
+        loop {
+            let r = self.0.recv_until(Some(deadline));
+            let msg = self.track(r)?;
+            if pred(self.output_str(&msg)) {
+                return Ok(self.output(msg));
+            }
+            self.1.push_back(msg);
+        }
+    }
+
+    /// Wait until the station is connected, returning the access point's BSSID
+    ///
+    /// This waits for `CTRL-EVENT-CONNECTED`, while a `CTRL-EVENT-ASSOC-REJECT`
+    /// ends the wait with the status code given by the access point. Other
+    /// messages stay buffered, as with [`ClientAttached::wait_for`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// wpa.request("RECONNECT").unwrap();
+    /// let bssid = wpa.wait_connected(Duration::from_secs(30)).unwrap();
+    /// println!("connected to {}", bssid);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::AssocRejected`] - The access point refused association
+    /// * [`Error::Io`] - Low-level I/O error
+    /// * [`Error::Timeout`] - Not connected within `timeout`
+    /// * [`Error::Utf8ToStr`] - Corrupted message or message with non-UTF8 characters
+    /// * [`Error::Wait`] - Failed to wait on underlying Unix socket
+    pub fn wait_connected(&mut self, timeout: Duration) -> Result<Bssid> {
+        let msg = self.wait_for(
+            |msg| matches!(Event::parse(msg), Event::Connected { .. } | Event::AssocReject { .. }),
+            timeout,
+        )?;
+        match Event::parse(&msg) {
+            Event::Connected { bssid, .. } => Ok(bssid),
+            Event::AssocReject { status_code, .. } => Err(Error::AssocRejected(status_code)),
+            _ => unreachable!("wait_for only returns matching messages"),
+        }
+    }
+
+    /// Discard control interface messages below the given priority
+    ///
+    /// Messages carry the `wpa_supplicant` log level as a `<N>` prefix, from
+    /// 0 (`MSG_EXCESSIVE`) to 5 (`MSG_ERROR`). Those below `min_priority` are
+    /// dropped as they are received, without being decoded, and already
+    /// buffered ones are discarded. The default of 0 keeps every message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// // Only keep MSG_INFO and more severe messages
+    /// wpa.set_min_priority(3);
+    /// ```
+    pub fn set_min_priority(&mut self, min_priority: u8) {
+        self.0.min_priority = min_priority;
+        self.1.retain(|msg| !below_priority(msg.as_bytes(), min_priority));
+    }
+
+    /// The reply to the last command, see [`Client::last_reply`]
+    ///
+    /// Receiving a control interface message clears it.
+    #[must_use]
+    pub fn last_reply(&self) -> Option<Cow<'_, str>> {
+        self.0.last_reply()
+    }
+
+    /// The current time according to the client's clock
+    pub(crate) fn now(&self) -> Instant {
+        self.0.clock.now()
+    }
+
+    /// Number of buffered control interface messages
+    pub(crate) fn buffered(&self) -> usize {
+        self.1.len()
+    }
+
+    /// Whether `wpa_supplicant` / `hostapd` is still expected to deliver control interface messages
+    ///
+    /// This is true after a successful `ATTACH`, and becomes false once an
+    /// operation reports that the other end of the control socket has gone
+    /// away (eg the supplicant was restarted). The registration does not
+    /// survive such a restart, so a supervisor should open and attach a new
+    /// client when this returns false.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// assert!(wpa.is_attached());
+    /// ```
+    #[must_use]
+    pub fn is_attached(&self) -> bool {
+        self.0.attached
+    }
+
+    /// A received message as returned to the caller, see [`ClientBuilder::strip_priority`]
+    fn output(&self, msg: String) -> String {
+        if self.0.strip_priority {
+            event::strip_priority(&msg).to_owned()
+        } else {
+            msg
+        }
+    }
+
+    /// Like [`ClientAttached::output`], borrowing the message
+    fn output_str<'a>(&self, msg: &'a str) -> &'a str {
+        if self.0.strip_priority {
+            event::strip_priority(msg)
+        } else {
+            msg
+        }
+    }
+
+    /// Record a lost connection to `wpa_supplicant` / `hostapd`
+    fn track<R>(&mut self, result: Result<R>) -> Result<R> {
+        if let Err(Error::Io(ref e)) = result {
+            if peer_gone(e) {
+                self.0.attached = false;
+            }
+        }
+        result
+    }
+}
+
+/// Non-blocking iteration over control interface messages, see [`ClientAttached::recv`]
+///
+/// `None` means no message is available right now, not that the stream has
+/// ended: iterating again later may yield more messages.
+///
+/// # Examples
+///
+/// ```
+/// let mut wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+/// for msg in wpa.by_ref().take(10) {
+///     println!("{}", msg.unwrap());
+/// }
+/// ```
+impl<T: Transport> Iterator for ClientAttached<T> {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().transpose()
+    }
+}
+
+impl<T: Transport + Send + 'static> ClientAttached<T> {
+    /// Move the connection to a thread that forwards every message to a bounded channel
+    ///
+    /// Messages arriving while the channel holds `capacity` unread messages
+    /// are dropped, and counted by [`EventThread::dropped`]. The thread ends,
+    /// closing the connection, after an error, once the receiver is dropped
+    /// and another message arrives, or when asked to by [`EventThread::stop`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let wpa = wpactrl::Client::builder().open().unwrap().attach().unwrap();
+    /// let (thread, events) = wpa.into_channel(64);
+    /// for msg in events.try_iter() {
+    ///     println!("{}", msg.unwrap());
+    /// }
+    /// thread.stop();
+    /// ```
+    pub fn into_channel(mut self, capacity: usize) -> (EventThread, Receiver<Result<String>>) {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let stop = Arc::new(AtomicBool::new(false));
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let handle = {
+            let stop = Arc::clone(&stop);
+            let dropped = Arc::clone(&dropped);
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let msg = match self.wait_event(CHANNEL_POLL_INTERVAL) {
+                        Ok(Some(msg)) => Ok(msg),
+                        Ok(None) => continue,
+                        Err(e) => Err(e),
+                    };
+                    let failed = msg.is_err();
+                    match sender.try_send(msg) {
+                        Ok(()) => {}
+                        Err(TrySendError::Full(_)) => {
+                            dropped.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(TrySendError::Disconnected(_)) => break,
+                    }
+                    if failed {
+                        break;
+                    }
+                }
+            })
+        };
+        let thread = EventThread {
+            handle: Some(handle),
+            stop,
+            dropped,
+        };
+        (thread, receiver)
+    }
+}
+
+/// The thread started by [`ClientAttached::into_channel`]
+///
+/// Dropping it asks the thread to stop without waiting for it.
+#[derive(Debug)]
+pub struct EventThread {
+    handle: Option<JoinHandle<()>>,
+    stop: Arc<AtomicBool>,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl EventThread {
+    /// Number of messages dropped so far because the channel was full
+    #[must_use]
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Whether the thread has ended, eg after an error
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.handle.as_ref().map_or(true, JoinHandle::is_finished)
+    }
+
+    /// Stop the thread and wait for it to close the connection
+    ///
+    /// This takes up to 100 milliseconds.
+    pub fn stop(mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            if let Err(panic) = handle.join() {
+                std::panic::resume_unwind(panic);
+            }
+        }
+    }
+}
+
+impl Drop for EventThread {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod test {
     use serial_test::serial;
     use super::*;
+    use crate::clock::MockClock;
+    use crate::transport::mock_ctrl;
 
     fn wpa_ctrl() -> Client {
         Client::builder().open().unwrap()
     }
 
+    /// A socket standing in for `wpa_supplicant`, unlinked when dropped
+    struct FakeServer {
+        path: PathBuf,
+        socket: UnixDatagram,
+    }
+
+    impl FakeServer {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("wpa_ctrl_test_{}-{}", name, std::process::id()));
+            let socket = UnixDatagram::bind(&path).unwrap();
+            Self { path, socket }
+        }
+    }
+
+    impl Drop for FakeServer {
+        fn drop(&mut self) {
+            std::fs::remove_file(&self.path).unwrap();
+        }
+    }
+
     #[test]
     #[serial]
     fn attach() {
@@ -396,4 +2394,778 @@ mod test {
         }
         wpa.detach().unwrap();
     }
+
+    #[test]
+    fn mock_request() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("PONG\n");
+        assert_eq!(wpa.request("PING").unwrap(), "PONG\n");
+        assert_eq!(mock.sent(), vec!["PING"]);
+    }
+
+    #[test]
+    fn mock_attached_request() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        mock.push_reply("OK\n");
+        assert_eq!(wpa.request("SCAN").unwrap(), "OK\n");
+        assert_eq!(wpa.recv().unwrap().as_deref(), Some("<3>CTRL-EVENT-SCAN-STARTED "));
+        assert_eq!(wpa.recv().unwrap(), None);
+        assert_eq!(mock.sent(), vec!["ATTACH", "SCAN"]);
+    }
+
+    #[test]
+    fn mock_recv_bytes() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply(b"<3>CTRL-EVENT-SSID \xff");
+        assert_eq!(wpa.recv_bytes().unwrap().as_deref(), Some(&b"<3>CTRL-EVENT-SSID \xff"[..]));
+        mock.push_reply(b"<3>CTRL-EVENT-SSID \xff");
+        assert!(matches!(wpa.recv(), Err(Error::Utf8ToStr(_))));
+    }
+
+    #[test]
+    fn mock_lossy_utf8() {
+        let (mock, mut wpa) = mock_ctrl();
+        wpa.0.lossy_utf8 = true;
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply(b"<3>CTRL-EVENT-SSID \xff");
+        assert_eq!(wpa.recv().unwrap().as_deref(), Some("<3>CTRL-EVENT-SSID \u{fffd}"));
+    }
+
+    #[test]
+    fn mock_ifname() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("PONG\n");
+        mock.push_reply("PONG\n");
+        mock.push_reply("PONG\n");
+        mock.push_reply("PONG\n");
+        wpa.request("PING").unwrap();
+        wpa.with_ifname("wlan1");
+        wpa.request("PING").unwrap();
+        wpa.request_iface("wlan2", "PING").unwrap();
+        wpa.with_ifname(None);
+        wpa.request("PING").unwrap();
+        assert_eq!(mock.sent(), vec!["PING", "IFNAME=wlan1 PING", "IFNAME=wlan2 PING", "PING"]);
+    }
+
+    #[test]
+    fn mock_recv_blocking() {
+        let (mock, mut wpa) = mock_ctrl();
+        wpa.0.recv_timeout = Some(Duration::from_millis(0));
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        assert_eq!(wpa.recv_blocking().unwrap(), "<3>CTRL-EVENT-SCAN-STARTED ");
+        assert!(matches!(wpa.recv_blocking(), Err(Error::Timeout)));
+    }
+
+    #[test]
+    fn mock_is_attached() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        assert!(wpa.is_attached());
+        mock.push_error(std::io::ErrorKind::ConnectionRefused);
+        assert!(matches!(wpa.recv(), Err(Error::Io(_))));
+        assert!(!wpa.is_attached());
+    }
+
+    #[test]
+    fn mock_request_bytes() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply(b"<3>CTRL-EVENT-SCAN-STARTED ");
+        mock.push_reply(b"\x01\xff\n");
+        assert_eq!(wpa.request_bytes(b"DATA_TEST_FRAME \xff").unwrap(), b"\x01\xff\n");
+        assert_eq!(mock.sent(), vec!["DATA_TEST_FRAME \u{fffd}"]);
+    }
+
+    #[test]
+    fn bind_name_taken() {
+        let dir = std::env::temp_dir();
+        let server = FakeServer::new("bind_name");
+        let taken = FakeServer::new("bind_name_taken");
+        let r = Client::builder()
+            .cli_path(&dir)
+            .ctrl_path(&server.path)
+            .bind_name(taken.path.file_name().unwrap().to_str().unwrap())
+            .open();
+        assert!(matches!(r, Err(Error::Io(ref e)) if e.kind() == std::io::ErrorKind::AddrInUse));
+    }
+
+    #[test]
+    fn mock_debug() {
+        let (mock, wpa) = mock_ctrl();
+        assert_eq!(format!("{:?}", wpa), "Client { ctrl_path: None, bind_path: None }");
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        mock.push_reply("PONG\n");
+        wpa.request("PING").unwrap();
+        assert_eq!(
+            format!("{:?}", wpa),
+            "ClientAttached { ctrl_path: None, bind_path: None, buffered: 1 }"
+        );
+    }
+
+    #[test]
+    fn socket_mode() {
+        let dir = std::env::temp_dir();
+        let server = FakeServer::new("socket_mode");
+        let wpa = Client::builder()
+            .cli_path(&dir)
+            .ctrl_path(&server.path)
+            .socket_mode(0o600)
+            .open()
+            .unwrap();
+        let mode = std::fs::metadata(wpa.0.filepath.as_ref().unwrap()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn ctrl_path_invalid() {
+        let dir = std::env::temp_dir();
+        let missing = dir.join(format!("wpa_ctrl_test_missing-{}", std::process::id()));
+        let r = Client::builder().cli_path(&dir).ctrl_path(&missing).open();
+        assert!(matches!(r, Err(Error::CtrlPathMissing(ref p)) if *p == missing));
+        let file = dir.join(format!("wpa_ctrl_test_file-{}", std::process::id()));
+        std::fs::write(&file, "").unwrap();
+        let r = Client::builder().cli_path(&dir).ctrl_path(&file).open();
+        assert!(matches!(r, Err(Error::CtrlPathNotSocket(ref p)) if *p == file));
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn invalid_path() {
+        let dir = std::env::temp_dir();
+        let nul = dir.join("wpa\0ctrl");
+        let r = Client::builder().cli_path(&dir).ctrl_path(&nul).open();
+        assert!(matches!(r, Err(Error::InvalidPath(ref p, _)) if *p == nul));
+        let long = dir.join("w".repeat(120));
+        let r = Client::builder().cli_path(&dir).ctrl_path(&long).open();
+        assert!(matches!(r, Err(Error::InvalidPath(ref p, _)) if *p == long));
+
+        let server = UnixDatagram::bind(dir.join(format!("wpa_ctrl_test_server-{}", std::process::id()))).unwrap();
+        let ctrl_path = server.local_addr().unwrap().as_pathname().unwrap().to_path_buf();
+        let r = Client::builder().cli_path(dir.join("w".repeat(120))).ctrl_path(&ctrl_path).open();
+        assert!(matches!(r, Err(Error::InvalidPath(..))));
+        std::fs::remove_file(&ctrl_path).unwrap();
+    }
+
+    #[test]
+    fn ctrl_path_dir() {
+        let dir = std::env::temp_dir().join(format!("wpa_ctrl_test_dir-{}", std::process::id()));
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join("not_a_socket"), "").unwrap();
+        let r = Client::builder().ctrl_path(&dir).open();
+        assert!(matches!(r, Err(Error::CtrlPathMissing(_))));
+        let wlan0 = UnixDatagram::bind(dir.join("wlan0")).unwrap();
+        let wpa = Client::builder().ctrl_path(&dir).open().unwrap();
+        assert_eq!(wpa.0.ctrl_path, Some(dir.join("wlan0")));
+        let wlan1 = UnixDatagram::bind(dir.join("wlan1")).unwrap();
+        let r = Client::builder().ctrl_path(&dir).open();
+        assert!(matches!(r, Err(Error::AmbiguousInterface(ref sockets)) if sockets.len() == 2));
+        drop((wlan0, wlan1));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn mock_check_alive() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        mock.push_reply("PONG\n");
+        assert!(wpa.check_alive(Duration::from_millis(10)).unwrap());
+        assert_eq!(wpa.recv().unwrap().as_deref(), Some("<3>CTRL-EVENT-SCAN-STARTED "));
+        assert!(!wpa.check_alive(Duration::from_millis(10)).unwrap());
+        mock.push_error(std::io::ErrorKind::ConnectionRefused);
+        assert!(!wpa.check_alive(Duration::from_millis(10)).unwrap());
+        assert!(!wpa.is_attached());
+    }
+
+    #[test]
+    fn socket() {
+        let server = FakeServer::new("socket");
+        let wpa = Client::builder()
+            .cli_path(std::env::temp_dir())
+            .ctrl_path(&server.path)
+            .open()
+            .unwrap();
+        let peer = wpa.socket().peer_addr().unwrap();
+        assert_eq!(peer.as_pathname(), Some(server.path.as_path()));
+    }
+
+    #[test]
+    fn mock_request_batch() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply("0\n");
+        mock.push_reply("<3>CTRL-EVENT-NETWORK-ADDED 0");
+        mock.push_reply("OK\n");
+        let replies = wpa.request_batch(&["ADD_NETWORK", "SET_NETWORK 0 ssid \"home\""]).unwrap();
+        assert_eq!(replies, vec!["0\n", "OK\n"]);
+        assert_eq!(wpa.recv().unwrap().as_deref(), Some("<3>CTRL-EVENT-NETWORK-ADDED 0"));
+        assert_eq!(mock.sent(), vec!["ATTACH", "ADD_NETWORK", "SET_NETWORK 0 ssid \"home\""]);
+    }
+
+    #[test]
+    fn blocking() {
+        let server = FakeServer::new("blocking");
+        let mut wpa = Client::builder()
+            .cli_path(std::env::temp_dir())
+            .ctrl_path(&server.path)
+            .nonblocking(false)
+            .open()
+            .unwrap();
+        assert_eq!(wpa.socket().read_timeout().unwrap(), Some(REQUEST_TIMEOUT));
+        let bind_path = wpa.0.filepath.clone().unwrap();
+        server.socket.send_to(b"<3>CTRL-EVENT-SCAN-STARTED ", &bind_path).unwrap();
+        server.socket.send_to(b"PONG\n", &bind_path).unwrap();
+        assert_eq!(wpa.request("PING").unwrap(), "PONG\n");
+        let mut buf = [0; 16];
+        let len = server.socket.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"PING");
+    }
+
+    #[test]
+    fn control_timeout() {
+        let server = FakeServer::new("control_timeout");
+        let mut wpa = Client::builder()
+            .cli_path(std::env::temp_dir())
+            .ctrl_path(&server.path)
+            .control_timeout(Duration::from_millis(100))
+            .nonblocking(false)
+            .open()
+            .unwrap();
+        assert!(wpa.socket().read_timeout().unwrap().unwrap() < REQUEST_TIMEOUT);
+        let start = Instant::now();
+        assert!(matches!(wpa.ping(), Err(Error::Timeout)));
+        assert!(start.elapsed() < REQUEST_TIMEOUT);
+        let bind_path = wpa.0.filepath.clone().unwrap();
+        server.socket.send_to(b"PONG\n", &bind_path).unwrap();
+        wpa.ping().unwrap();
+        assert!(matches!(wpa.attach(), Err(Error::Timeout)));
+    }
+
+    #[test]
+    fn close() {
+        let server = FakeServer::new("close");
+        let wpa = Client::builder()
+            .cli_path(std::env::temp_dir())
+            .ctrl_path(&server.path)
+            .open()
+            .unwrap();
+        let bind_path = wpa.0.filepath.clone().unwrap();
+        assert!(bind_path.exists());
+        wpa.close().unwrap();
+        assert!(!bind_path.exists());
+
+        let wpa = Client::builder()
+            .cli_path(std::env::temp_dir())
+            .ctrl_path(&server.path)
+            .open()
+            .unwrap();
+        std::fs::remove_file(wpa.0.filepath.as_ref().unwrap()).unwrap();
+        assert!(matches!(wpa.close(), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn access_error() {
+        let path = Path::new("/var/run/wpa_supplicant/wlan0");
+        let denied = super::access_error(std::io::ErrorKind::PermissionDenied.into(), path);
+        assert!(matches!(denied, Error::PermissionDenied(ref p) if p == path));
+        assert!(denied.to_string().contains("elevated privileges"));
+        let other = super::access_error(std::io::ErrorKind::ConnectionRefused.into(), path);
+        assert!(matches!(other, Error::Io(_)));
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn autobind() {
+        use std::os::linux::net::SocketAddrExt;
+        let server = FakeServer::new("autobind");
+        let wpa = Client::builder().ctrl_path(&server.path).autobind().open().unwrap();
+        assert!(wpa.0.filepath.is_none());
+        assert!(wpa.socket().local_addr().unwrap().as_abstract_name().is_some());
+        wpa.socket().send(b"PING").unwrap();
+        let mut buf = [0; 16];
+        let (len, addr) = server.socket.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"PING");
+        server.socket.send_to_addr(b"PONG\n", &addr).unwrap();
+        let len = wpa.socket().recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"PONG\n");
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn verify_peer() {
+        let uid = unsafe { libc::getuid() };
+        let server = FakeServer::new("verify_peer");
+        let mut wpa = Client::builder().ctrl_path(&server.path).verify_peer(uid).open().unwrap();
+        let bound = wpa.0.filepath.clone().unwrap();
+        server.socket.send_to(b"PONG\n", &bound).unwrap();
+        assert_eq!(wpa.0.recv().unwrap().as_deref(), Some("PONG\n"));
+
+        let mut wpa = Client::builder().ctrl_path(&server.path).verify_peer(uid + 1).open().unwrap();
+        let bound = wpa.0.filepath.clone().unwrap();
+        server.socket.send_to(b"PONG\n", &bound).unwrap();
+        assert!(matches!(wpa.0.recv(), Err(Error::UntrustedPeer(Some(u))) if u == uid));
+        assert_eq!(wpa.0.recv().unwrap(), None);
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn verify_peer_udp() {
+        let server = std::net::UdpSocket::bind((std::net::Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let r = Client::builder().verify_peer(0).udp(server.local_addr().unwrap());
+        assert!(matches!(r, Err(Error::CredentialsUnavailable)));
+    }
+
+    #[test]
+    fn terminate_on_drop() {
+        let server = FakeServer::new("terminate_on_drop");
+        let wpa = Client::builder()
+            .cli_path(std::env::temp_dir())
+            .ctrl_path(&server.path)
+            .terminate_on_drop(true)
+            .open()
+            .unwrap();
+        drop(wpa);
+        let mut buf = [0; 16];
+        let len = server.socket.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"TERMINATE");
+    }
+
+    #[test]
+    fn mock_min_priority() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply("<2>CTRL-EVENT-SCAN-STARTED ");
+        mock.push_reply("<3>CTRL-EVENT-SCAN-RESULTS ");
+        mock.push_reply("PONG\n");
+        wpa.request("PING").unwrap();
+        wpa.set_min_priority(3);
+        assert_eq!(wpa.recv().unwrap().as_deref(), Some("<3>CTRL-EVENT-SCAN-RESULTS "));
+        mock.push_reply(b"<1>\xff");
+        mock.push_reply("<4>WPS-TIMEOUT ");
+        assert_eq!(wpa.recv().unwrap().as_deref(), Some("<4>WPS-TIMEOUT "));
+        assert_eq!(wpa.recv().unwrap(), None);
+    }
+
+    #[test]
+    fn mock_on_exchange() {
+        let (mock, mut wpa) = mock_ctrl();
+        let exchanges = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = exchanges.clone();
+        wpa.0.on_exchange = Some(Box::new(move |cmd: &str, reply: &str| {
+            log.lock().unwrap().push(format!("{:?} {:?}", cmd, reply));
+        }));
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        mock.push_reply("OK\n");
+        wpa.request("SCAN").unwrap();
+        mock.push_reply("OK\n");
+        wpa.request("SET_NETWORK 0 psk \"secret\"").unwrap();
+        assert_eq!(
+            *exchanges.lock().unwrap(),
+            vec![
+                "\"ATTACH\" \"OK\\n\"",
+                "\"\" \"<3>CTRL-EVENT-SCAN-STARTED \"",
+                "\"SCAN\" \"OK\\n\"",
+                "\"SET_NETWORK 0 psk ***\" \"OK\\n\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn wait_event() {
+        let server = FakeServer::new("wait_event");
+        let wpa = Client::builder()
+            .cli_path(std::env::temp_dir())
+            .ctrl_path(&server.path)
+            .open()
+            .unwrap();
+        let bind_path = wpa.0.filepath.clone().unwrap();
+        server.socket.send_to(b"OK\n", &bind_path).unwrap();
+        let mut wpa = wpa.attach().unwrap();
+        assert_eq!(wpa.wait_event(Duration::from_millis(10)).unwrap(), None);
+        server.socket.send_to(b"<3>CTRL-EVENT-SCAN-STARTED ", &bind_path).unwrap();
+        assert_eq!(
+            wpa.wait_event(Duration::from_secs(1)).unwrap().as_deref(),
+            Some("<3>CTRL-EVENT-SCAN-STARTED ")
+        );
+    }
+
+    #[test]
+    fn mock_clock_timeout() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let clock = MockClock::ticking(Duration::from_secs(1));
+        let mut wpa = wpa.with_clock(clock.clone()).attach().unwrap();
+        let start = clock.now();
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        assert!(matches!(
+            wpa.wait_for(|msg| msg.contains("SCAN-RESULTS"), Duration::from_secs(30)),
+            Err(Error::Timeout)
+        ));
+        let elapsed = clock.now() - start;
+        assert!(elapsed >= Duration::from_secs(30) && elapsed < Duration::from_secs(40));
+        assert_eq!(wpa.buffered(), 1);
+    }
+
+    #[test]
+    fn mock_recv_all() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply("<3>one");
+        mock.push_reply("<3>two");
+        mock.push_reply("PONG\n");
+        wpa.request("PING").unwrap();
+        mock.push_reply("<3>three");
+        mock.push_reply("PONG\n");
+        wpa.request("PING").unwrap();
+        mock.push_reply("<3>four");
+        assert_eq!(wpa.recv_all().unwrap(), vec!["<3>one", "<3>two", "<3>three", "<3>four"]);
+        assert!(wpa.recv_all().unwrap().is_empty());
+    }
+
+    #[test]
+    fn mock_request_nowait() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        wpa.request_nowait("NOTE hello").unwrap();
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        mock.push_reply("OK\n");
+        mock.push_reply("PONG\n");
+        assert_eq!(wpa.request("PING").unwrap(), "PONG\n");
+        assert_eq!(mock.sent(), vec!["ATTACH", "NOTE hello", "PING"]);
+        assert_eq!(wpa.recv().unwrap().as_deref(), Some("<3>CTRL-EVENT-SCAN-STARTED "));
+    }
+
+    #[test]
+    fn mock_flush() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        mock.push_reply("FAIL\n");
+        assert_eq!(wpa.flush().unwrap(), 2);
+        assert_eq!(wpa.flush().unwrap(), 0);
+        mock.push_reply("PONG\n");
+        assert_eq!(wpa.request("PING").unwrap(), "PONG\n");
+    }
+
+    #[test]
+    fn mock_into_channel() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let wpa = wpa.attach().unwrap();
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        mock.push_reply("<3>CTRL-EVENT-SCAN-RESULTS ");
+        mock.push_reply("<3>WPS-TIMEOUT ");
+        let (thread, events) = wpa.into_channel(2);
+        let start = Instant::now();
+        while thread.dropped() == 0 {
+            assert!(start.elapsed() < REQUEST_TIMEOUT);
+            std::thread::yield_now();
+        }
+        assert_eq!(events.recv().unwrap().unwrap(), "<3>CTRL-EVENT-SCAN-STARTED ");
+        assert_eq!(events.recv().unwrap().unwrap(), "<3>CTRL-EVENT-SCAN-RESULTS ");
+        mock.push_reply("<3>WPS-SUCCESS ");
+        assert_eq!(events.recv().unwrap().unwrap(), "<3>WPS-SUCCESS ");
+        assert!(!thread.is_finished());
+        assert_eq!(thread.dropped(), 1);
+        thread.stop();
+        assert!(events.recv().is_err());
+    }
+
+    #[test]
+    fn mock_malformed_priority() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        mock.push_reply("<garbage\n");
+        assert_eq!(wpa.request("GET bogus").unwrap(), "<garbage\n");
+        mock.push_reply("<>OK\n");
+        assert_eq!(wpa.request("GET bogus").unwrap(), "<>OK\n");
+        assert_eq!(wpa.recv().unwrap().as_deref(), Some("<3>CTRL-EVENT-SCAN-STARTED "));
+        assert_eq!(wpa.recv().unwrap(), None);
+    }
+
+    #[test]
+    fn mock_last_reply() {
+        let (mock, mut wpa) = mock_ctrl();
+        assert_eq!(wpa.last_reply(), None);
+        mock.push_reply("wpa_state=COMPLETED\nbogus\n");
+        assert!(matches!(wpa.status(), Err(Error::Parse(_))));
+        assert_eq!(wpa.last_reply().as_deref(), Some("wpa_state=COMPLETED\nbogus\n"));
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        assert_eq!(wpa.last_reply().as_deref(), Some("OK\n"));
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        wpa.recv().unwrap();
+        assert_eq!(wpa.last_reply(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "past a 5 byte datagram")]
+    fn mock_received_bounds() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        mock.push_reply("PONG\n");
+        assert_eq!(wpa.request("PING").unwrap(), "PONG\n");
+        assert_eq!(wpa.0.received(5), b"PONG\n");
+        // The tail of the longer event is still in the buffer, but no longer valid
+        let _ = wpa.0.received(6);
+    }
+
+    #[test]
+    fn mock_request_with_events() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        mock.push_reply("<3>CTRL-EVENT-SCAN-RESULTS ");
+        mock.push_reply("PONG\n");
+        let mut events = Vec::new();
+        let reply = wpa.request_with_events("PING", |msg| events.push(msg.to_owned())).unwrap();
+        assert_eq!(reply, "PONG\n");
+        assert_eq!(events, ["<3>CTRL-EVENT-SCAN-STARTED ", "<3>CTRL-EVENT-SCAN-RESULTS "]);
+    }
+
+    #[test]
+    fn mock_pipeline() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply("PONG\n");
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        mock.push_reply(b"\xff\n");
+        mock.push_reply("FAIL\n");
+        let replies = wpa.pipeline(&["PING", "GET bogus", "SCAN"]).unwrap();
+        assert_eq!(mock.sent()[1..], ["PING", "GET bogus", "SCAN"]);
+        assert_eq!(replies[0].as_deref().unwrap(), "PONG\n");
+        assert!(matches!(replies[1], Err(Error::Utf8ToStr(_))));
+        assert_eq!(replies[2].as_deref().unwrap(), "FAIL\n");
+        assert_eq!(wpa.recv().unwrap().as_deref(), Some("<3>CTRL-EVENT-SCAN-STARTED "));
+
+        // Replies still due after a failure are not mistaken for later ones
+        mock.push_reply("PONG\n");
+        mock.push_error(std::io::ErrorKind::ConnectionRefused);
+        assert!(matches!(wpa.pipeline(&["PING", "PING", "PING"]), Err(Error::Io(_))));
+        mock.push_reply("PONG\n");
+        mock.push_reply("PONG\n");
+        mock.push_reply("OK\n");
+        assert_eq!(wpa.request("NOTE x").unwrap(), "OK\n");
+    }
+
+    #[test]
+    fn mock_iterator() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        mock.push_reply("<3>CTRL-EVENT-SCAN-RESULTS ");
+        mock.push_reply("<3>WPS-TIMEOUT ");
+        let msgs: Vec<_> = wpa.by_ref().take(2).collect::<Result<_>>().unwrap();
+        assert_eq!(msgs, vec!["<3>CTRL-EVENT-SCAN-STARTED ", "<3>CTRL-EVENT-SCAN-RESULTS "]);
+        assert_eq!(wpa.next().unwrap().unwrap(), "<3>WPS-TIMEOUT ");
+        assert!(wpa.next().is_none());
+        mock.push_reply("<3>WPS-SUCCESS ");
+        assert_eq!(wpa.next().unwrap().unwrap(), "<3>WPS-SUCCESS ");
+    }
+
+    #[test]
+    fn mock_iterator_strip_priority() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        wpa.0.strip_priority = true;
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        assert_eq!(wpa.next().unwrap().unwrap(), "CTRL-EVENT-SCAN-STARTED ");
+    }
+
+    #[test]
+    fn mock_trim_replies() {
+        let (mock, mut wpa) = mock_ctrl();
+        wpa.0.trim_replies = true;
+        mock.push_reply("PONG\n");
+        mock.push_reply("a\nb\n\n");
+        mock.push_reply("OK\n");
+        mock.push_reply("OK\n");
+        assert_eq!(wpa.request("PING").unwrap(), "PONG");
+        assert_eq!(wpa.request_borrowed("LIST").unwrap(), "a\nb\n");
+        // Typed helpers still see the reply as sent
+        wpa.reconfigure().unwrap();
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        mock.push_reply("FAIL\n");
+        mock.push_reply("");
+        assert_eq!(wpa.request_batch(&["SCAN", "STA-NEXT x"]).unwrap(), vec!["FAIL", ""]);
+        assert_eq!(wpa.recv().unwrap().as_deref(), Some("<3>CTRL-EVENT-SCAN-STARTED "));
+    }
+
+    #[test]
+    fn mock_truncated() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply(vec![b'a'; BUF_SIZE + 1]);
+        assert!(matches!(wpa.request("SCAN_RESULTS"), Err(Error::Truncated)));
+        mock.push_reply(vec![b'a'; BUF_SIZE - 1]);
+        assert_eq!(wpa.request("SCAN_RESULTS").unwrap().len(), BUF_SIZE - 1);
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        let mut event = b"<3>".to_vec();
+        event.resize(BUF_SIZE, b'a');
+        mock.push_reply(&event);
+        mock.push_reply("PONG\n");
+        assert_eq!(wpa.request("PING").unwrap(), "PONG\n");
+        assert_eq!(wpa.recv().unwrap(), None);
+        mock.push_reply(&event);
+        assert!(matches!(wpa.recv(), Err(Error::Truncated)));
+    }
+
+    #[test]
+    fn mock_strip_priority() {
+        let (mock, mut wpa) = mock_ctrl();
+        wpa.0.strip_priority = true;
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply("<3>CTRL-EVENT-SCAN-STARTED ");
+        mock.push_reply("PONG\n");
+        wpa.request("PING").unwrap();
+        mock.push_reply("<12>CTRL-EVENT-SCAN-RESULTS ");
+        mock.push_reply("no priority");
+        assert_eq!(wpa.recv().unwrap().as_deref(), Some("CTRL-EVENT-SCAN-STARTED "));
+        assert_eq!(wpa.recv_blocking().unwrap(), "CTRL-EVENT-SCAN-RESULTS ");
+        assert_eq!(wpa.recv_all().unwrap(), vec!["no priority"]);
+    }
+
+    #[test]
+    fn mock_wait_connected() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply("<3>CTRL-EVENT-SCAN-RESULTS ");
+        mock.push_reply("<3>CTRL-EVENT-CONNECTED - Connection to 00:11:22:33:44:55 completed [id=0 id_str=]");
+        let bssid = wpa.wait_connected(Duration::from_millis(10)).unwrap();
+        assert_eq!(bssid.to_string(), "00:11:22:33:44:55");
+        mock.push_reply("<3>CTRL-EVENT-ASSOC-REJECT bssid=00:11:22:33:44:55 status_code=17");
+        assert!(matches!(wpa.wait_connected(Duration::from_millis(10)), Err(Error::AssocRejected(17))));
+        assert!(matches!(wpa.wait_connected(Duration::from_millis(0)), Err(Error::Timeout)));
+        assert_eq!(wpa.recv().unwrap().as_deref(), Some("<3>CTRL-EVENT-SCAN-RESULTS "));
+    }
+
+    #[test]
+    fn mock_wait_for() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply("<3>one");
+        mock.push_reply("<3>two");
+        mock.push_reply("<3>three");
+        let timeout = Duration::from_millis(10);
+        assert_eq!(wpa.wait_for(|msg| msg.ends_with("two"), timeout).unwrap(), "<3>two");
+        assert!(matches!(wpa.wait_for(|msg| msg.ends_with("four"), timeout), Err(Error::Timeout)));
+        assert_eq!(wpa.wait_for(|msg| msg.ends_with("three"), timeout).unwrap(), "<3>three");
+        assert_eq!(wpa.recv_all().unwrap(), vec!["<3>one"]);
+    }
+
+    #[test]
+    fn mock_wait_for_strip_priority() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        wpa.0.strip_priority = true;
+        mock.push_reply("<3>one");
+        mock.push_reply("<3>two");
+        mock.push_reply("<3>CTRL-EVENT-CONNECTED - Connection to 00:11:22:33:44:55 completed ");
+        let timeout = Duration::from_millis(10);
+        assert_eq!(wpa.wait_for(|msg| msg.ends_with("two"), timeout).unwrap(), "two");
+        assert_eq!(wpa.wait_for(|msg| msg.starts_with("one"), timeout).unwrap(), "one");
+        let msg = wpa.wait_for(|msg| msg.starts_with("CTRL-EVENT-CONNECTED"), timeout).unwrap();
+        assert!(msg.starts_with("CTRL-EVENT-CONNECTED"));
+    }
+
+    #[test]
+    fn mock_request_borrowed() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("PONG\n");
+        mock.push_reply(b"\xff\n");
+        assert_eq!(wpa.request_borrowed("PING").unwrap(), "PONG\n");
+        wpa.0.lossy_utf8 = true;
+        assert!(matches!(wpa.request_borrowed("PING"), Err(Error::Utf8ToStr(_))));
+    }
+
+    #[test]
+    fn ifname() {
+        let server = FakeServer::new("ifname");
+        let ifname = server.path.file_name().unwrap().to_str().unwrap();
+        let wpa = Client::builder()
+            .cli_path(std::env::temp_dir())
+            .ctrl_dir(std::env::temp_dir())
+            .ifname(ifname)
+            .open()
+            .unwrap();
+        assert_eq!(wpa.0.ctrl_path.as_ref(), Some(&server.path));
+        let wpa = Client::builder()
+            .cli_path(std::env::temp_dir())
+            .ctrl_path(&server.path)
+            .ifname("wpa_ctrl_test_missing")
+            .open()
+            .unwrap();
+        assert_eq!(wpa.0.ctrl_path.as_ref(), Some(&server.path));
+    }
+
+    #[test]
+    fn mock_detach_keeping_buffer() {
+        let (mock, wpa) = mock_ctrl();
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach().unwrap();
+        mock.push_reply("<3>one");
+        mock.push_reply("PONG\n");
+        wpa.request("PING").unwrap();
+        mock.push_reply("<3>two");
+        mock.push_reply("OK\n");
+        let (wpa, buffer) = wpa.detach_keeping_buffer().unwrap();
+        assert_eq!(buffer, vec!["<3>one", "<3>two"]);
+        mock.push_reply("OK\n");
+        let mut wpa = wpa.attach_with_buffer(buffer).unwrap();
+        mock.push_reply("<3>three");
+        assert_eq!(wpa.recv_all().unwrap(), vec!["<3>one", "<3>two", "<3>three"]);
+        assert_eq!(mock.sent(), vec!["ATTACH", "PING", "DETACH", "ATTACH"]);
+    }
+
+    #[test]
+    fn mock_empty_reply() {
+        let (mock, mut wpa) = mock_ctrl();
+        mock.push_reply("");
+        assert_eq!(wpa.request("STA-NEXT 00:11:22:33:44:55").unwrap(), "");
+        assert!(matches!(wpa.0.request_timeout("PING", Duration::from_millis(0), |_: &str| ()), Err(Error::Timeout)));
+    }
+
+    #[test]
+    fn set_ctrl_path() {
+        let wlan0 = FakeServer::new("set_ctrl_path_wlan0");
+        let wlan1 = FakeServer::new("set_ctrl_path_wlan1");
+        let mut wpa = Client::builder()
+            .cli_path(std::env::temp_dir())
+            .ctrl_path(&wlan0.path)
+            .open()
+            .unwrap();
+        let bind_path = wpa.0.filepath.clone();
+        wpa.set_ctrl_path(&wlan1.path).unwrap();
+        let peer = wpa.socket().peer_addr().unwrap();
+        assert_eq!(peer.as_pathname(), Some(wlan1.path.as_path()));
+        assert_eq!(wpa.0.ctrl_path.as_ref(), Some(&wlan1.path));
+        assert_eq!(wpa.0.filepath, bind_path);
+        let missing = std::env::temp_dir().join("wpa_ctrl_test_set_ctrl_path_missing");
+        assert!(matches!(wpa.set_ctrl_path(&missing), Err(Error::CtrlPathMissing(_))));
+    }
 }